@@ -0,0 +1,192 @@
+//! Definition and implementation of the size-limited and counting response body readers.
+
+// std imports
+use std::{
+    io::{self, Read},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+/// A reader which fails once more than `limit` bytes have been read from the inner reader.
+pub struct LimitedReader<R> {
+    inner: R,
+    limit: Option<u64>,
+    read: u64,
+    exceeded: bool,
+}
+
+/// Function implementations for LimitedReader.
+impl<R: Read> LimitedReader<R> {
+    /// Create a new LimitedReader. A limit of `None` reads everything.
+    pub fn new(inner: R, limit: Option<u64>) -> LimitedReader<R> {
+        LimitedReader {
+            inner,
+            limit,
+            read: 0,
+            exceeded: false,
+        }
+    }
+
+    /// Return whether reading stopped because the limit was exceeded.
+    pub fn exceeded(&self) -> bool {
+        self.exceeded
+    }
+}
+
+impl<R: Read> Read for LimitedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let limit = match self.limit {
+            Some(limit) => limit,
+            None => {
+                let len = self.inner.read(buf)?;
+                self.read += len as u64;
+                return Ok(len);
+            }
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let remaining = limit - self.read;
+        if remaining == 0 {
+            // Only fail if the body actually continues past the limit
+            let mut probe = [0; 1];
+            if self.inner.read(&mut probe)? > 0 {
+                self.exceeded = true;
+                return Err(io::Error::other(format!(
+                    "body exceeds the limit of {} bytes",
+                    limit
+                )));
+            }
+            return Ok(0);
+        }
+        let max = buf.len().min(remaining.min(usize::MAX as u64) as usize);
+        let len = self.inner.read(&mut buf[..max])?;
+        self.read += len as u64;
+        Ok(len)
+    }
+}
+
+/// A shared count of the bytes read through a CountingReader, readable after the reader has been moved away.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ByteCount(Arc<AtomicU64>);
+
+/// Function implementations for ByteCount.
+impl ByteCount {
+    /// Return the number of bytes read so far.
+    pub(crate) fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A reader which counts the bytes read from the inner reader.
+pub(crate) struct CountingReader<R> {
+    inner: R,
+    count: ByteCount,
+}
+
+/// Function implementations for CountingReader.
+impl<R: Read> CountingReader<R> {
+    /// Create a new CountingReader adding to the given count.
+    pub(crate) fn new(inner: R, count: ByteCount) -> CountingReader<R> {
+        CountingReader { inner, count }
+    }
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.count.0.fetch_add(len as u64, Ordering::Relaxed);
+        Ok(len)
+    }
+}
+
+/// A reader which keeps a copy of what was read from the inner reader, so it can be read again.
+/// The copy is dropped once it would grow past the limit, so large bodies aren't held in memory twice.
+pub(crate) struct RecordingReader<R> {
+    inner: R,
+    recorded: Option<Vec<u8>>,
+    limit: usize,
+}
+
+/// Function implementations for RecordingReader.
+impl<R: Read> RecordingReader<R> {
+    /// Create a new RecordingReader, recording up to `limit` bytes.
+    pub(crate) fn new(inner: R, limit: usize) -> RecordingReader<R> {
+        RecordingReader {
+            inner,
+            recorded: Some(Vec::new()),
+            limit,
+        }
+    }
+
+    /// Return what was read so far, `None` if it was more than the limit, along with the inner reader to read the rest from.
+    pub(crate) fn into_parts(self) -> (Option<Vec<u8>>, R) {
+        (self.recorded, self.inner)
+    }
+}
+
+impl<R: Read> Read for RecordingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        if let Some(recorded) = &mut self.recorded {
+            if recorded.len() + len > self.limit {
+                self.recorded = None;
+            } else {
+                recorded.extend_from_slice(&buf[..len]);
+            }
+        }
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limited_reader() {
+        let mut body = Vec::new();
+        let mut reader = LimitedReader::new("abcd".as_bytes(), Some(4));
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"abcd");
+        assert!(!reader.exceeded());
+
+        let mut body = Vec::new();
+        let mut reader = LimitedReader::new("abcde".as_bytes(), Some(4));
+        assert!(reader.read_to_end(&mut body).is_err());
+        assert!(reader.exceeded());
+        assert_eq!(body, b"abcd");
+
+        let mut body = Vec::new();
+        let mut reader = LimitedReader::new("abcde".as_bytes(), None);
+        reader.read_to_end(&mut body).unwrap();
+        assert_eq!(body, b"abcde");
+    }
+
+    #[test]
+    fn test_counting_reader() {
+        let count = ByteCount::default();
+        let mut reader = CountingReader::new("abcde".as_bytes(), count.clone());
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).unwrap();
+        drop(reader);
+        assert_eq!(count.get(), 5);
+    }
+
+    #[test]
+    fn test_recording_reader() {
+        let mut reader = RecordingReader::new("abcde".as_bytes(), 4);
+        let mut start = [0; 2];
+        reader.read_exact(&mut start).unwrap();
+        let (recorded, mut rest) = reader.into_parts();
+        let mut recorded = recorded.unwrap();
+        rest.read_to_end(&mut recorded).unwrap();
+        assert_eq!(recorded, b"abcde");
+
+        let mut reader = RecordingReader::new("abcde".as_bytes(), 4);
+        reader.read_exact(&mut [0; 5]).unwrap();
+        assert_eq!(reader.into_parts().0, None);
+    }
+}
@@ -0,0 +1,1179 @@
+//! Definition and implementation of the reusable feed client.
+
+// std imports
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Read},
+    sync::{Arc, Mutex},
+    thread,
+    time::Instant,
+};
+
+// third-party imports
+use chrono::prelude::*;
+use reqwest::{
+    blocking::{Client, Response},
+    header::{HeaderMap, CONTENT_ENCODING, LOCATION, RETRY_AFTER},
+    Url,
+};
+use rss::Channel;
+
+// Local Imports
+use super::auth::{same_origin, split_userinfo, Credentials};
+use super::body::{ByteCount, CountingReader, LimitedReader};
+use super::compression::{decompress_detected, Compression};
+use super::config::FetchConfig;
+use super::diagnostics::Diagnostics;
+use super::encoding::{decode, detect_encoding};
+use super::error::FetchError;
+use super::fetched::FetchedChannel;
+use super::fetcher::{FetchResponse, Fetcher};
+use super::functions::read_decompressed_channel;
+use super::health::HealthTracker;
+use super::rate_limit::{HostLimiter, Permit};
+use super::redirect::Redirect;
+use super::retry::parse_retry_after;
+use super::robots::Robots;
+use super::source::is_local;
+
+/// The largest robots.txt read, 512 KiB.
+const MAX_ROBOTS_SIZE: u64 = 512 * 1024;
+
+/// A response body being read, decompressed according to its `Content-Encoding` and limited to the configured size,
+/// along with its `Content-Type` and the redirects followed to get it.
+pub(crate) struct OpenedBody {
+    body: LimitedReader<Box<dyn Read>>,
+    content_type: Option<String>,
+    redirects: Vec<Redirect>,
+    received: ByteCount,
+}
+
+/// An HTTP client for fetching feeds, built once from a FetchConfig and reused across calls.
+/// Requests to the same host are spaced out according to the RateLimit, also when the client is shared between threads.
+pub struct FeedClient {
+    client: Client,
+    origin_headers: HeaderMap,
+    config: FetchConfig,
+    limiter: Arc<HostLimiter>,
+    robots: Mutex<HashMap<String, Arc<Robots>>>,
+}
+
+/// The body of a response, holding on to the rate limit permit of its host until it has been read.
+pub(crate) struct HttpBody {
+    response: Response,
+    _permit: Option<Permit>,
+}
+
+/// Function implementations for HttpBody.
+impl HttpBody {
+    /// Return the response the body belongs to.
+    pub(crate) fn response(&self) -> &Response {
+        &self.response
+    }
+}
+
+impl Read for HttpBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.response.read(buf)
+    }
+}
+
+/// Function implementations for FeedClient.
+impl FeedClient {
+    /// Create a new FeedClient from the given configuration.
+    pub fn new(config: FetchConfig) -> Result<FeedClient, Box<dyn Error>> {
+        Ok(FeedClient {
+            client: config.build_client()?,
+            origin_headers: config.origin_headers()?,
+            limiter: HostLimiter::new(config.host_rate_limit().clone()),
+            config,
+            robots: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Return the configuration the client was built from.
+    pub fn config(&self) -> &FetchConfig {
+        &self.config
+    }
+
+    /// Fetch the contents from the given URL, retrying retryable failures according to the RetryPolicy.
+    /// The body is transcoded to UTF-8 from the encoding detected from its byte order mark,
+    /// `Content-Type` header, XML declaration or HTML `<meta>` tag.
+    /// Failed requests are returned as a boxed FetchError.
+    pub fn get(&self, uri: &str) -> Result<String, Box<dyn Error>> {
+        let (uri, userinfo) = split_userinfo(uri);
+        let uri = uri.as_str();
+        let mut opened = self.open(uri, userinfo.as_ref(), &mut Diagnostics::new(uri))?;
+        let mut contents = Vec::new();
+        if let Err(e) = opened.body.read_to_end(&mut contents) {
+            return Err(Box::new(self.body_error(uri, &opened.body, e)));
+        }
+        let prefix = &contents[..contents.len().min(1024)];
+        let detected = detect_encoding(prefix, opened.content_type.as_deref());
+        Ok(decode(&contents, &detected))
+    }
+
+    /// Send a request to the given URL and return a reader over its body, decompressed according to its `Content-Encoding`
+    /// and limited to the configured size, along with the redirects followed.
+    /// The status, headers and final URL of the last response are recorded in the given Diagnostics, also when it failed.
+    /// Failures before the body is read are retried according to the RetryPolicy.
+    /// Only `http://` and `https://` URLs are fetched, use `load_channel` to read local files and `data:` URLs.
+    /// The URL must not contain userinfo, its credentials are passed separately so they never end up in errors.
+    pub(crate) fn open(
+        &self,
+        uri: &str,
+        userinfo: Option<&Credentials>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<OpenedBody, FetchError> {
+        if is_local(uri) {
+            return Err(FetchError::UnsupportedScheme {
+                url: uri.to_string(),
+            });
+        }
+        let policy = self.config.retry_policy();
+        let mut retry = 0;
+        loop {
+            match self.follow(uri, userinfo, diagnostics) {
+                Ok((body, redirects)) => {
+                    let limit = self.config.body_limit();
+                    if let (Some(limit), Some(len)) = (limit, body.response().content_length()) {
+                        if len > limit {
+                            return Err(FetchError::BodyTooLarge {
+                                url: uri.to_string(),
+                                limit,
+                            });
+                        }
+                    }
+                    let content_type = diagnostics.header("content-type").map(str::to_string);
+                    let received = ByteCount::default();
+                    let body = decompress(uri, body, received.clone(), diagnostics)?;
+                    return Ok(OpenedBody {
+                        body: LimitedReader::new(body, limit),
+                        content_type,
+                        redirects,
+                        received,
+                    });
+                }
+                Err(e) if e.is_retryable() && retry < policy.retries() => {
+                    match policy.delay(retry, e.retry_after()) {
+                        Some(delay) => thread::sleep(delay),
+                        None => return Err(e),
+                    }
+                    retry += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Send a request to the given URL, following up to the configured number of redirects.
+    /// Credentials from the userinfo and the configured `Authorization`, `Cookie` and `Proxy-Authorization` headers
+    /// are only sent to the origin of the URL, configured credentials only to the URLs they are configured for.
+    /// Redirect responses without a `Location`, e.g. 304 Not Modified, fail with their status code.
+    fn follow(
+        &self,
+        uri: &str,
+        userinfo: Option<&Credentials>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<(HttpBody, Vec<Redirect>), FetchError> {
+        let mut redirects: Vec<Redirect> = Vec::new();
+        let mut url = uri.to_string();
+        loop {
+            let is_origin = same_origin(uri, &url);
+            let credentials = userinfo
+                .filter(|_| is_origin)
+                .or_else(|| self.config.credentials_for(&url));
+            let headers = Some(&self.origin_headers).filter(|_| is_origin);
+            diagnostics.set_final_url(&url);
+            let body = self
+                .send(&url, credentials, headers, diagnostics)
+                .map_err(|error| match error {
+                    FetchError::Status {
+                        url,
+                        status,
+                        retry_after,
+                        ..
+                    } => FetchError::Status {
+                        url,
+                        requested_url: uri.to_string(),
+                        status,
+                        retry_after,
+                    },
+                    error => error,
+                })?;
+            let response = body.response();
+            let location = match redirect_location(response) {
+                Some(location) => location,
+                None if response.status().is_redirection() => {
+                    return Err(FetchError::Status {
+                        url,
+                        requested_url: uri.to_string(),
+                        status: response.status().as_u16(),
+                        retry_after: None,
+                    })
+                }
+                None => return Ok((body, redirects)),
+            };
+            if redirects.len() >= self.config.redirect_limit() {
+                if self.config.redirect_limit() == 0 {
+                    return Err(FetchError::Status {
+                        url,
+                        requested_url: uri.to_string(),
+                        status: response.status().as_u16(),
+                        retry_after: None,
+                    });
+                }
+                return Err(FetchError::Transport {
+                    url: uri.to_string(),
+                    message: format!("more than {} redirects", self.config.redirect_limit()),
+                    retryable: false,
+                });
+            }
+            redirects.push(Redirect::new(
+                response.status().as_u16(),
+                &url,
+                location.as_str(),
+            ));
+            url = location.to_string();
+        }
+    }
+
+    /// Send a single request to the given URL with the given credentials and extra headers,
+    /// failing on unsuccessful status codes other than redirects.
+    /// Waits for the host to become available first, and checks its robots.txt if configured to.
+    /// The status and headers of the response are recorded in the given Diagnostics.
+    fn send(
+        &self,
+        uri: &str,
+        credentials: Option<&Credentials>,
+        headers: Option<&HeaderMap>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<HttpBody, FetchError> {
+        let url = Url::parse(uri).ok();
+        let host = url
+            .as_ref()
+            .and_then(|url| url.host_str())
+            .map(str::to_string);
+        if let Some(url) = &url {
+            if self.limiter.respects_robots_txt() && !self.robots_allow(url) {
+                return Err(FetchError::Disallowed {
+                    url: uri.to_string(),
+                });
+            }
+        }
+        let permit =
+            match &host {
+                Some(host) => Some(self.limiter.acquire(host).map_err(|retry_after| {
+                    FetchError::RateLimited {
+                        url: uri.to_string(),
+                        retry_after,
+                    }
+                })?),
+                None => None,
+            };
+        let mut request = self.client.get(uri);
+        if let Some(credentials) = credentials {
+            request = credentials
+                .apply(request)
+                .map_err(|message| FetchError::Transport {
+                    url: uri.to_string(),
+                    message,
+                    retryable: false,
+                })?;
+        }
+        let mut request = request
+            .build()
+            .map_err(|e| FetchError::from_reqwest(uri, e))?;
+        // Credentials take precedence over the configured headers, like they do over the default headers
+        if let Some(headers) = headers {
+            for name in headers.keys() {
+                if !request.headers().contains_key(name) {
+                    for value in headers.get_all(name) {
+                        request.headers_mut().append(name, value.clone());
+                    }
+                }
+            }
+        }
+        let sent = Instant::now();
+        let response = self
+            .client
+            .execute(request)
+            .map_err(|e| FetchError::from_reqwest(uri, e))?;
+        diagnostics.set_time_to_first_byte(sent.elapsed());
+        let status = response.status();
+        diagnostics.set_status(status.as_u16());
+        diagnostics.record_headers(
+            response
+                .headers()
+                .iter()
+                .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+        );
+        if !status.is_success() && !status.is_redirection() {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| parse_retry_after(value, Utc::now()));
+            if let (Some(host), Some(retry_after)) = (&host, retry_after) {
+                self.limiter.back_off(host, retry_after);
+            }
+            return Err(FetchError::Status {
+                url: uri.to_string(),
+                requested_url: uri.to_string(),
+                status: status.as_u16(),
+                retry_after,
+            });
+        }
+        Ok(HttpBody {
+            response,
+            _permit: permit,
+        })
+    }
+
+    /// Return whether the robots.txt of the host of the given URL allows fetching it.
+    /// The robots.txt of every host is fetched once, hosts without one allow everything.
+    fn robots_allow(&self, url: &Url) -> bool {
+        let origin = url.origin().ascii_serialization();
+        let cached = self
+            .robots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&origin)
+            .cloned();
+        let robots = match cached {
+            Some(robots) => robots,
+            None => {
+                let robots = Arc::new(self.fetch_robots(url).unwrap_or_default());
+                if let (Some(host), Some(delay)) = (url.host_str(), robots.crawl_delay()) {
+                    self.limiter.set_interval(host, delay);
+                }
+                self.robots
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .insert(origin, Arc::clone(&robots));
+                robots
+            }
+        };
+        let mut path = url.path().to_string();
+        if let Some(query) = url.query() {
+            path = format!("{}?{}", path, query);
+        }
+        robots.is_allowed(&path)
+    }
+
+    /// Fetch and parse the robots.txt of the host of the given URL, if it has one.
+    /// Redirects are followed up to the configured number of redirects.
+    fn fetch_robots(&self, url: &Url) -> Option<Robots> {
+        let mut robots_url = url.join("/robots.txt").ok()?;
+        let mut redirects = 0;
+        let response = loop {
+            let _permit = self.limiter.acquire(robots_url.host_str()?).ok()?;
+            let response = self.client.get(robots_url.clone()).send().ok()?;
+            match redirect_location(&response) {
+                Some(location) if redirects < self.config.redirect_limit() => {
+                    redirects += 1;
+                    robots_url = location;
+                }
+                Some(_) => return None,
+                None => break response,
+            }
+        };
+        if !response.status().is_success() {
+            return None;
+        }
+        let mut contents = Vec::new();
+        LimitedReader::new(response, Some(MAX_ROBOTS_SIZE))
+            .read_to_end(&mut contents)
+            .ok()?;
+        Some(Robots::parse(
+            &String::from_utf8_lossy(&contents),
+            self.config.user_agent_string(),
+        ))
+    }
+
+    /// Turn an error raised while reading a body into a FetchError.
+    fn body_error(
+        &self,
+        uri: &str,
+        body: &LimitedReader<Box<dyn Read>>,
+        error: io::Error,
+    ) -> FetchError {
+        match self.config.body_limit() {
+            Some(limit) if body.exceeded() => FetchError::BodyTooLarge {
+                url: uri.to_string(),
+                limit,
+            },
+            _ => FetchError::Transport {
+                url: uri.to_string(),
+                message: error.to_string(),
+                retryable: true,
+            },
+        }
+    }
+
+    /// Fetch the feed at the given URL, parsing it while the body is being read.
+    pub fn get_channel(&self, uri: &str) -> Result<Channel, Box<dyn Error>> {
+        Ok(self.fetch_channel(uri)?.into_channel())
+    }
+
+    /// Fetch the feed at the given URL, reporting the redirects followed along with the channel.
+    /// Credentials in the userinfo of the URL are sent as Basic authentication and stripped from the reported URLs.
+    /// Use `FetchedChannel::subscription_update` to learn whether the feed has moved,
+    /// and `SubscriptionUpdate::from_error` to learn whether it is gone.
+    pub fn fetch_channel(&self, uri: &str) -> Result<FetchedChannel, Box<dyn Error>> {
+        self.fetch_channel_diagnosed(uri).0
+    }
+
+    /// Same as `fetch_channel`, also returning the diagnostics of failed fetches,
+    /// e.g. the status and headers of an error response or the warnings about a feed which couldn't be parsed.
+    pub fn fetch_channel_diagnosed(
+        &self,
+        uri: &str,
+    ) -> (Result<FetchedChannel, Box<dyn Error>>, Diagnostics) {
+        let (uri, userinfo) = split_userinfo(uri);
+        let mut diagnostics = Diagnostics::new(&uri);
+        let fetched = self.read_fetched(&uri, userinfo.as_ref(), &mut diagnostics);
+        let diagnostics = diagnostics.finish();
+        let fetched = fetched.map(|fetched| fetched.with_diagnostics(diagnostics.clone()));
+        (fetched, diagnostics)
+    }
+
+    /// Fetch and parse the feed at the given URL, recording what happened in the given Diagnostics.
+    fn read_fetched(
+        &self,
+        uri: &str,
+        userinfo: Option<&Credentials>,
+        diagnostics: &mut Diagnostics,
+    ) -> Result<FetchedChannel, Box<dyn Error>> {
+        let mut opened = self.open(uri, userinfo, diagnostics)?;
+        let channel = read_decompressed_channel(
+            &mut opened.body,
+            opened.content_type.as_deref(),
+            diagnostics,
+        );
+        diagnostics.set_bytes_received(opened.received.get());
+        if opened.body.exceeded() {
+            return Err(Box::new(FetchError::BodyTooLarge {
+                url: uri.to_string(),
+                limit: self.config.body_limit().unwrap_or_default(),
+            }));
+        }
+        Ok(FetchedChannel::new(channel?, uri, opened.redirects))
+    }
+
+    /// Same as `get_channels`, reporting the redirects followed along with every channel.
+    pub fn fetch_channels(&self, urls: &[&str]) -> Vec<Result<FetchedChannel, Box<dyn Error>>> {
+        Fetcher::fetch_channels(self, urls)
+    }
+
+    /// Fetch the contents from the given URLs and parse it as an RSS feed. Returning a vector of channels.
+    pub fn get_channels(&self, urls: &[&str]) -> Vec<Result<Channel, Box<dyn Error>>> {
+        Fetcher::get_channels(self, urls)
+    }
+
+    /// Same as `get_channels`, recording the outcome of every fetch in the given HealthTracker.
+    pub fn get_channels_tracked(
+        &self,
+        urls: &[&str],
+        tracker: &mut HealthTracker,
+    ) -> Vec<Result<Channel, Box<dyn Error>>> {
+        Fetcher::get_channels_tracked(self, urls, tracker)
+    }
+}
+
+/// Fetches over HTTP, reporting bodies over the size limit as `FetchError::BodyTooLarge`.
+/// URLs with any other scheme fail with `FetchError::UnsupportedScheme`.
+impl Fetcher for FeedClient {
+    fn fetch(&self, url: &str) -> Result<FetchResponse<'_>, Box<dyn Error>> {
+        let (url, userinfo) = split_userinfo(url);
+        let mut diagnostics = Diagnostics::new(&url);
+        let opened = self.open(&url, userinfo.as_ref(), &mut diagnostics)?;
+        Ok(FetchResponse::new(opened.body)
+            .content_type(opened.content_type.as_deref())
+            .redirects(opened.redirects)
+            .diagnostics(diagnostics))
+    }
+
+    fn get(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        FeedClient::get(self, url)
+    }
+
+    fn fetch_channel(&self, url: &str) -> Result<FetchedChannel, Box<dyn Error>> {
+        FeedClient::fetch_channel(self, url)
+    }
+}
+
+/// Return the url a redirect response points to, resolved against the url of the response and without userinfo.
+fn redirect_location(response: &Response) -> Option<Url> {
+    if !response.status().is_redirection() {
+        return None;
+    }
+    let location = response.headers().get(LOCATION)?.to_str().ok()?;
+    let location = response.url().join(location).ok()?;
+    // Credentials in the userinfo of a redirect are dropped, so they are neither sent nor reported
+    let (location, _) = split_userinfo(location.as_str());
+    Url::parse(&location).ok()
+}
+
+/// Return a reader decompressing the given body according to its `Content-Encoding` header,
+/// and then according to its magic number, counting the compressed bytes received in the given ByteCount.
+/// Decompressing everything here lets the size limit apply to the decompressed body.
+fn decompress(
+    uri: &str,
+    body: HttpBody,
+    received: ByteCount,
+    diagnostics: &mut Diagnostics,
+) -> Result<Box<dyn Read>, FetchError> {
+    let content_encoding = body
+        .response()
+        .headers()
+        .get(CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let body = CountingReader::new(body, received);
+    let error = |message: String| FetchError::Transport {
+        url: uri.to_string(),
+        message,
+        retryable: false,
+    };
+    let body: Box<dyn Read> = match Compression::from_content_encoding(&content_encoding) {
+        Ok(Some(compression)) => {
+            diagnostics.set_compression(compression);
+            compression
+                .decoder(body)
+                .map_err(|e| error(e.to_string()))?
+        }
+        Ok(None) => Box::new(body),
+        Err(encoding) => return Err(error(format!("unsupported Content-Encoding {}", encoding))),
+    };
+    let (body, compression) = decompress_detected(body).map_err(|e| error(e.to_string()))?;
+    if let Some(compression) = compression {
+        diagnostics.set_compression(compression);
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetching::diagnostics::FeedFormat;
+    use crate::fetching::rate_limit::RateLimit;
+    use crate::fetching::redirect::SubscriptionUpdate;
+    use crate::fetching::retry::RetryPolicy;
+    use flate2::write::GzEncoder;
+    use std::{
+        fs,
+        io::{Read, Write},
+        net::TcpListener,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    /// Serve the given raw HTTP responses on a local port, one per connection,
+    /// sending every received request back over the channel.
+    fn serve(responses: Vec<String>, delay: Duration) -> (String, mpsc::Receiver<String>) {
+        serve_bytes(
+            responses.into_iter().map(String::into_bytes).collect(),
+            delay,
+        )
+    }
+
+    /// Same as `serve`, for responses which aren't valid UTF-8.
+    fn serve_bytes(responses: Vec<Vec<u8>>, delay: Duration) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/feed", listener.local_addr().unwrap());
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = [0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                let _ = sender.send(String::from_utf8_lossy(&request[..len]).to_string());
+                thread::sleep(delay);
+                let _ = stream.write_all(&response);
+            }
+        });
+        (url, receiver)
+    }
+
+    /// Build a raw HTTP response with the given status line, extra headers and body.
+    fn response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    /// Same as `response`, for bodies which aren't valid UTF-8.
+    fn response_bytes(status: &str, headers: &str, body: &[u8]) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            headers,
+            body.len()
+        )
+        .into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    #[test]
+    /// Test wether the client sends the configured user agent and headers
+    fn test_feed_client_headers() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let (url, request) = serve(vec![response("200 OK", "", &f)], Duration::ZERO);
+        let config = FetchConfig::new()
+            .user_agent("ferss-test")
+            .header("X-Test", "1");
+        let client = FeedClient::new(config).unwrap();
+
+        let results = client.get_channels(&[url.as_str()]);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].as_ref().unwrap().items().len(), 3);
+
+        let request = request.recv().unwrap().to_lowercase();
+        assert!(request.contains("user-agent: ferss-test"));
+        assert!(request.contains("x-test: 1"));
+    }
+
+    #[test]
+    /// Test wether the client gives up on servers that don't respond in time
+    fn test_feed_client_timeout() {
+        let (url, _request) = serve(vec![response("200 OK", "", "")], Duration::from_secs(2));
+        let config = FetchConfig::new()
+            .timeout(Some(Duration::from_millis(200)))
+            .retry(RetryPolicy::none());
+        let client = FeedClient::new(config).unwrap();
+        assert!(client.get(&url).is_err());
+    }
+
+    #[test]
+    /// Test wether the client retries retryable failures and honors Retry-After
+    fn test_feed_client_retry() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let responses = vec![
+            response("503 Service Unavailable", "Retry-After: 0\r\n", ""),
+            response("200 OK", "", &f),
+        ];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new().retry(RetryPolicy::new().max_retries(1));
+        let client = FeedClient::new(config).unwrap();
+        let mut tracker = HealthTracker::new();
+
+        let results = client.get_channels_tracked(&[url.as_str()], &mut tracker);
+        assert!(results[0].is_ok());
+        assert_eq!(requests.iter().count(), 2);
+        assert_eq!(tracker.health(&url).consecutive_failures(), 0);
+    }
+
+    #[test]
+    /// Test wether the client gives up right away when Retry-After is longer than the maximum retry delay
+    fn test_feed_client_retry_after_too_long() {
+        let responses = vec![response(
+            "503 Service Unavailable",
+            "Retry-After: 120\r\n",
+            "",
+        )];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let policy = RetryPolicy::new()
+            .max_retries(3)
+            .max_delay(Duration::from_secs(5));
+        let client = FeedClient::new(FetchConfig::new().retry(policy)).unwrap();
+
+        let started = Instant::now();
+        let error = client.get(&url).unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(5));
+        let error = error.downcast_ref::<FetchError>().unwrap();
+        assert_eq!(error.status(), Some(503));
+        assert_eq!(error.retry_after(), Some(Duration::from_secs(120)));
+        assert_eq!(requests.iter().count(), 1);
+    }
+
+    #[test]
+    /// Test wether the client doesn't retry client errors and reports them as a FetchError
+    fn test_feed_client_no_retry() {
+        let responses = vec![response("404 Not Found", "", "")];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new().retry(RetryPolicy::new().max_retries(3));
+        let client = FeedClient::new(config).unwrap();
+        let mut tracker = HealthTracker::new();
+
+        let results = client.get_channels_tracked(&[url.as_str()], &mut tracker);
+        let error = results[0].as_ref().unwrap_err();
+        let error = error.downcast_ref::<FetchError>().unwrap();
+        assert_eq!(error.status(), Some(404));
+        assert!(!error.is_retryable());
+        assert_eq!(requests.iter().count(), 1);
+        assert_eq!(tracker.health(&url).consecutive_failures(), 1);
+    }
+
+    #[test]
+    /// Test wether the client refuses bodies larger than the configured limit
+    fn test_feed_client_body_limit() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let config = FetchConfig::new().max_body_size(Some(100));
+        let client = FeedClient::new(config).unwrap();
+
+        // Rejected up front because of the Content-Length
+        let (url, _requests) = serve(vec![response("200 OK", "", &f)], Duration::ZERO);
+        let error = client.get_channel(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { limit: 100, .. })
+        ));
+
+        // Rejected while streaming when the length isn't known in advance
+        let chunked = format!("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n{}", f);
+        let (url, _requests) = serve(vec![chunked.clone(), chunked], Duration::ZERO);
+        let error = client.get_channel(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { limit: 100, .. })
+        ));
+        let error = client.get(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { limit: 100, .. })
+        ));
+    }
+
+    #[test]
+    /// Test wether the client transcodes feeds using the charset of the Content-Type header
+    fn test_feed_client_encoding() {
+        let f = fs::read("./resources/testing/example_windows_1252.rss").unwrap();
+        let mut raw = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/rss+xml; charset=windows-1252\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            f.len()
+        )
+        .into_bytes();
+        raw.extend_from_slice(&f);
+        let (url, _requests) = serve_bytes(vec![raw], Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+        let channel = client.get_channel(&url).unwrap();
+        assert_eq!(channel.items()[0].title(), Some("Café “quoted” – dash"));
+    }
+
+    #[test]
+    /// Test wether the client reports the redirects it followed and suggests moving permanently redirected feeds
+    fn test_feed_client_redirects() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let responses = vec![
+            response("301 Moved Permanently", "Location: /moved\r\n", ""),
+            response("302 Found", "Location: /temporary\r\n", ""),
+            response("200 OK", "", &f),
+        ];
+        let (url, _requests) = serve(responses, Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+
+        let fetched = client.fetch_channel(&url).unwrap();
+        let moved = url.replace("/feed", "/moved");
+        assert_eq!(fetched.channel().items().len(), 3);
+        assert_eq!(fetched.final_url(), url.replace("/feed", "/temporary"));
+        assert_eq!(fetched.redirects().len(), 2);
+        assert!(!fetched.is_permanently_redirected());
+        assert_eq!(
+            fetched.subscription_update(),
+            Some(SubscriptionUpdate::Moved {
+                from: url.clone(),
+                to: moved
+            })
+        );
+
+        // Redirects beyond the limit are refused
+        let redirect = response("308 Permanent Redirect", "Location: /feed\r\n", "");
+        let (url, _requests) = serve(vec![redirect.clone(), redirect], Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new().max_redirects(1)).unwrap();
+        assert!(client.fetch_channel(&url).is_err());
+    }
+
+    #[test]
+    /// Test wether the client suggests marking feeds which respond with 410 Gone as dead
+    fn test_feed_client_gone() {
+        let (url, _requests) = serve(vec![response("410 Gone", "", "")], Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert_eq!(
+            SubscriptionUpdate::from_error(error.as_ref()),
+            Some(SubscriptionUpdate::Gone { url: url.clone() })
+        );
+
+        // A feed redirected to a url which is gone is gone itself
+        let responses = vec![
+            response("301 Moved Permanently", "Location: /moved\r\n", ""),
+            response("410 Gone", "", ""),
+        ];
+        let (url, _requests) = serve(responses, Duration::ZERO);
+        let error = client.fetch_channel(&url).unwrap_err();
+        let fetch_error = error.downcast_ref::<FetchError>().unwrap();
+        assert_eq!(fetch_error.url(), url.replace("/feed", "/moved"));
+        assert_eq!(
+            SubscriptionUpdate::from_error(error.as_ref()),
+            Some(SubscriptionUpdate::Gone { url })
+        );
+    }
+
+    #[test]
+    /// Test wether the client holds off hosts which responded with 429 and a Retry-After
+    fn test_feed_client_rate_limited() {
+        let responses = vec![response(
+            "429 Too Many Requests",
+            "Retry-After: 120\r\n",
+            "",
+        )];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new()
+            .retry(RetryPolicy::none())
+            .rate_limit(RateLimit::new().max_wait(Duration::from_secs(1)));
+        let client = FeedClient::new(config).unwrap();
+
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FetchError>().unwrap().status(),
+            Some(429)
+        );
+        let error = client.fetch_channel(&url).unwrap_err();
+        let error = error.downcast_ref::<FetchError>().unwrap();
+        assert!(matches!(error, FetchError::RateLimited { .. }));
+        assert!(error.retry_after().unwrap() > Duration::from_secs(60));
+        assert_eq!(requests.iter().count(), 1);
+    }
+
+    #[test]
+    /// Test wether the client obeys robots.txt when configured to
+    fn test_feed_client_robots_txt() {
+        let robots = "User-agent: *\nDisallow: /feed\n";
+        let (url, requests) = serve(vec![response("200 OK", "", robots)], Duration::ZERO);
+        let config = FetchConfig::new().rate_limit(RateLimit::new().respect_robots_txt(true));
+        let client = FeedClient::new(config).unwrap();
+
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::Disallowed { .. })
+        ));
+        // The robots.txt is only fetched once
+        assert!(client.fetch_channel(&url).is_err());
+        let requests: Vec<String> = requests.iter().collect();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("GET /robots.txt"));
+
+        // Redirected robots.txt are followed
+        let responses = vec![
+            response(
+                "301 Moved Permanently",
+                "Location: /robots-moved.txt\r\n",
+                "",
+            ),
+            response("200 OK", "", robots),
+        ];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new().rate_limit(RateLimit::new().respect_robots_txt(true));
+        let client = FeedClient::new(config).unwrap();
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::Disallowed { .. })
+        ));
+        let requests: Vec<String> = requests.iter().collect();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[1].starts_with("GET /robots-moved.txt"));
+    }
+
+    #[test]
+    /// Test wether credentials in the url are sent, but kept out of errors and reported urls
+    fn test_feed_client_userinfo() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let responses = vec![
+            response("200 OK", "", &f),
+            response("401 Unauthorized", "", ""),
+        ];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let url = url.replace("http://", "http://user:secret@");
+        let config = FetchConfig::new().retry(RetryPolicy::none());
+        let client = FeedClient::new(config).unwrap();
+
+        let fetched = client.fetch_channel(&url).unwrap();
+        assert!(!fetched.url().contains("secret"));
+        // dXNlcjpzZWNyZXQ= is user:secret
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("authorization: basic dxnlcjpzzwnyzxq="));
+
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FetchError>().unwrap().status(),
+            Some(401)
+        );
+        assert!(!error.to_string().contains("secret"));
+        assert!(!format!("{:?}", error).contains("secret"));
+    }
+
+    #[test]
+    /// Test wether configured credentials are sent to the matching urls only, and not across redirects to other hosts
+    fn test_feed_client_credentials() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let (target, target_requests) = serve(vec![response("200 OK", "", &f)], Duration::ZERO);
+        let redirect = format!("Location: {}\r\n", target);
+        let responses = vec![
+            response("200 OK", "", &f),
+            response("302 Found", &redirect, ""),
+        ];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new()
+            .credentials(&url, Credentials::bearer("token"))
+            .credentials(&url.replace("/feed", "/"), Credentials::cookie("session=1"))
+            .credentials(
+                &url.replace("/feed", "/fee"),
+                Credentials::header("X-Key", "2"),
+            );
+        let client = FeedClient::new(config).unwrap();
+
+        assert!(client.fetch_channel(&url).is_ok());
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("authorization: bearer token"));
+        assert!(!request.contains("cookie"));
+        assert!(!request.contains("x-key"));
+
+        assert!(client.fetch_channel(&url).is_ok());
+        assert!(requests
+            .recv()
+            .unwrap()
+            .to_lowercase()
+            .contains("authorization"));
+        let request = target_requests.recv().unwrap().to_lowercase();
+        assert!(!request.contains("authorization"));
+    }
+
+    #[test]
+    /// Test wether sensitive configured headers are dropped on redirects to other origins, and redirects without a Location fail
+    fn test_feed_client_redirect_headers() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let (target, target_requests) = serve(vec![response("200 OK", "", &f)], Duration::ZERO);
+        let redirect = format!("Location: {}\r\n", target);
+        let responses = vec![
+            response("302 Found", "Location: /same\r\n", ""),
+            response("302 Found", &redirect, ""),
+        ];
+        let (url, requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new()
+            .header("Authorization", "Bearer token")
+            .header("Cookie", "session=1")
+            .header("X-Test", "1");
+        let client = FeedClient::new(config).unwrap();
+
+        assert!(client.fetch_channel(&url).is_ok());
+        let requests: Vec<String> = requests.iter().collect();
+        assert_eq!(requests.len(), 2);
+        for request in requests {
+            let request = request.to_lowercase();
+            assert!(request.contains("authorization: bearer token"));
+            assert!(request.contains("cookie: session=1"));
+        }
+        let request = target_requests.recv().unwrap().to_lowercase();
+        assert!(!request.contains("authorization"));
+        assert!(!request.contains("cookie"));
+        assert!(request.contains("x-test: 1"));
+
+        let (url, _requests) = serve(vec![response("304 Not Modified", "", "")], Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FetchError>().unwrap().status(),
+            Some(304)
+        );
+    }
+
+    #[test]
+    /// Test wether credentials in the userinfo of a redirect are neither sent nor reported
+    fn test_feed_client_redirect_userinfo() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let responses = vec![
+            response("200 OK", "", &f),
+            response("404 Not Found", "", ""),
+        ];
+        let (target, target_requests) = serve(responses, Duration::ZERO);
+        let location = target.replacen("http://", "http://me:s3cret@", 1);
+        let redirect = format!("Location: {}\r\n", location);
+        let responses = vec![
+            response("302 Found", &redirect, ""),
+            response("302 Found", &redirect, ""),
+        ];
+        let (url, _requests) = serve(responses, Duration::ZERO);
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+
+        let fetched = client.fetch_channel(&url).unwrap();
+        assert_eq!(fetched.redirects()[0].to(), target);
+        assert_eq!(fetched.diagnostics().final_url(), target);
+        let request = target_requests.recv().unwrap().to_lowercase();
+        assert!(!request.contains("authorization"));
+
+        let error = client.fetch_channel(&url).unwrap_err();
+        assert_eq!(error.downcast_ref::<FetchError>().unwrap().url(), target);
+        assert!(!error.to_string().contains("s3cret"));
+    }
+
+    #[test]
+    /// Test wether the client asks for compressed bodies and decompresses them
+    fn test_feed_client_compression() {
+        let gzip = fs::read("./resources/testing/example.rss.gz").unwrap();
+        let brotli = fs::read("./resources/testing/example.rss.br").unwrap();
+        let zstd = fs::read("./resources/testing/example.rss.zst").unwrap();
+        let responses = vec![
+            response_bytes("200 OK", "Content-Encoding: gzip\r\n", &gzip),
+            response_bytes("200 OK", "Content-Encoding: br\r\n", &brotli),
+            // A compressed file served without a Content-Encoding
+            response_bytes("200 OK", "Content-Type: application/zstd\r\n", &zstd),
+            response_bytes("200 OK", "Content-Encoding: compress\r\n", &gzip),
+        ];
+        let (url, requests) = serve_bytes(responses, Duration::ZERO);
+        let config = FetchConfig::new().retry(RetryPolicy::none());
+        let client = FeedClient::new(config).unwrap();
+
+        for _ in 0..3 {
+            assert_eq!(client.get_channel(&url).unwrap().items().len(), 3);
+        }
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(request.contains("accept-encoding: gzip, deflate, br, zstd"));
+        let error = client.get_channel(&url).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::Transport { .. })
+        ));
+    }
+
+    #[test]
+    /// Test wether the size limit applies to the decompressed body, also when it's compressed without a Content-Encoding
+    fn test_feed_client_compressed_body_limit() {
+        let gzip = |contents: &[u8]| {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::best());
+            encoder.write_all(contents).unwrap();
+            encoder.finish().unwrap()
+        };
+        let bomb = gzip(&vec![b' '; 1024 * 1024]);
+        let nested = gzip(&bomb);
+        let responses = vec![
+            response_bytes("200 OK", "", &bomb),
+            response_bytes("200 OK", "", &bomb),
+            response_bytes("200 OK", "Content-Encoding: gzip\r\n", &nested),
+        ];
+        let (url, _requests) = serve_bytes(responses, Duration::ZERO);
+        let config = FetchConfig::new()
+            .max_body_size(Some(64 * 1024))
+            .retry(RetryPolicy::none());
+        let client = FeedClient::new(config).unwrap();
+
+        let errors = [
+            client.get(&url).unwrap_err(),
+            client.fetch_channel(&url).unwrap_err(),
+            client.fetch_channel(&url).unwrap_err(),
+        ];
+        for error in errors {
+            assert!(matches!(
+                error.downcast_ref::<FetchError>(),
+                Some(FetchError::BodyTooLarge { limit: 65536, .. })
+            ));
+        }
+    }
+
+    #[test]
+    /// Test wether the client records the diagnostics of every fetch
+    fn test_feed_client_diagnostics() {
+        let gzip = fs::read("./resources/testing/example.rss.gz").unwrap();
+        let headers = "Content-Type: application/rss+xml\r\nContent-Encoding: gzip\r\nETag: \"1\"\r\nX-Other: 1\r\n";
+        let (url, _requests) = serve_bytes(
+            vec![response_bytes("200 OK", headers, &gzip)],
+            Duration::ZERO,
+        );
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+
+        let fetched = client.fetch_channel(&url).unwrap();
+        let diagnostics = fetched.diagnostics();
+        assert_eq!(diagnostics.final_url(), url);
+        assert_eq!(diagnostics.status(), Some(200));
+        assert_eq!(diagnostics.header("ETag"), Some("\"1\""));
+        assert_eq!(diagnostics.header("x-other"), None);
+        assert_eq!(diagnostics.bytes_received(), gzip.len() as u64);
+        assert_eq!(diagnostics.compression(), Some(Compression::Gzip));
+        assert_eq!(diagnostics.format(), FeedFormat::Rss);
+        assert_eq!(diagnostics.encoding().unwrap().name(), "UTF-8");
+        assert!(diagnostics.time_to_first_byte().unwrap() <= diagnostics.total_time());
+        assert!(diagnostics.warnings().is_empty());
+    }
+
+    #[test]
+    /// Test wether the client refuses to read anything but http and https URLs
+    fn test_feed_client_unsupported_scheme() {
+        let client = FeedClient::new(FetchConfig::new()).unwrap();
+        for url in [
+            "/etc/hostname",
+            "example.com/feed",
+            "ftp://example.com/feed",
+            "data:,x",
+        ] {
+            let error = client.get(url).unwrap_err();
+            assert!(matches!(
+                error.downcast_ref::<FetchError>(),
+                Some(FetchError::UnsupportedScheme { .. })
+            ));
+            assert_eq!(
+                error.to_string(),
+                format!("{} is not an http or https URL", url)
+            );
+            assert!(client.fetch_channel(url).is_err());
+            assert!(Fetcher::fetch(&client, url).is_err());
+        }
+    }
+
+    #[test]
+    /// Test wether failed fetches report their diagnostics too
+    fn test_feed_client_failure_diagnostics() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let html = "<html><body>Not a feed</body></html>";
+        let responses = vec![
+            response(
+                "404 Not Found",
+                "Content-Type: text/html\r\nCache-Control: no-store\r\n",
+                html,
+            ),
+            response("200 OK", "", &f),
+            response("200 OK", "Content-Type: text/html\r\n", html),
+        ];
+        let (url, _requests) = serve(responses, Duration::ZERO);
+        let config = FetchConfig::new()
+            .max_body_size(Some(1024))
+            .retry(RetryPolicy::none());
+        let client = FeedClient::new(config).unwrap();
+
+        let (fetched, diagnostics) = client.fetch_channel_diagnosed(&url);
+        let error = fetched.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<FetchError>().unwrap().status(),
+            Some(404)
+        );
+        assert_eq!(diagnostics.status(), Some(404));
+        assert_eq!(diagnostics.header("cache-control"), Some("no-store"));
+        assert_eq!(diagnostics.final_url(), url);
+
+        let (fetched, diagnostics) = client.fetch_channel_diagnosed(&url);
+        assert!(matches!(
+            fetched.unwrap_err().downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { .. })
+        ));
+        assert_eq!(diagnostics.status(), Some(200));
+        let length = f.len().to_string();
+        assert_eq!(diagnostics.header("content-length"), Some(length.as_str()));
+
+        let (fetched, diagnostics) = client.fetch_channel_diagnosed(&url);
+        assert!(fetched.is_err());
+        assert_eq!(diagnostics.format(), FeedFormat::Unknown);
+        assert!(diagnostics
+            .warnings()
+            .iter()
+            .any(|warning| warning.contains("text/html")));
+    }
+}
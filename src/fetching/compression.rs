@@ -0,0 +1,168 @@
+//! Definition and implementation of the decompression of compressed bodies and files.
+
+// std imports
+use std::io::{self, Cursor, Read};
+
+// third-party imports
+use brotli_decompressor::Decompressor;
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use zstd::stream::read::Decoder as ZstdDecoder;
+
+/// The `Accept-Encoding` header sent when compression is enabled.
+pub(crate) const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// The size of the buffers of the brotli decoder.
+const BROTLI_BUFFER_SIZE: usize = 8 * 1024;
+
+/// A compression format bodies and files are decompressed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    /// zlib-wrapped or raw deflate.
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+/// Function implementations for Compression.
+impl Compression {
+    /// Return the Compression named by the given `Content-Encoding` header.
+    /// `Ok(None)` means the body isn't compressed, unknown encodings are returned as the error.
+    pub fn from_content_encoding(content_encoding: &str) -> Result<Option<Compression>, String> {
+        match content_encoding.trim().to_ascii_lowercase().as_str() {
+            "" | "identity" => Ok(None),
+            "gzip" | "x-gzip" => Ok(Some(Compression::Gzip)),
+            "deflate" => Ok(Some(Compression::Deflate)),
+            "br" => Ok(Some(Compression::Brotli)),
+            "zstd" => Ok(Some(Compression::Zstd)),
+            other => Err(other.to_string()),
+        }
+    }
+
+    /// Return the Compression of the given file name from its extension, e.g. `feed.xml.gz`.
+    pub fn from_extension(name: &str) -> Option<Compression> {
+        let (_, extension) = name.rsplit_once('.')?;
+        match extension.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "br" => Some(Compression::Brotli),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Detect the Compression of a body from its first bytes.
+    /// Brotli has no magic number and is never detected.
+    pub fn detect(prefix: &[u8]) -> Option<Compression> {
+        match prefix {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            [first, second, ..] if is_zlib_header(*first, *second) => Some(Compression::Deflate),
+            _ => None,
+        }
+    }
+
+    /// Return a reader decompressing the given reader.
+    pub fn decoder<'a>(self, reader: impl Read + 'a) -> io::Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            Compression::Gzip => Box::new(GzDecoder::new(reader)),
+            Compression::Deflate => {
+                // Servers disagree on whether deflate is zlib-wrapped, so look at the header
+                let mut reader = reader;
+                let mut header = Vec::with_capacity(2);
+                (&mut reader).take(2).read_to_end(&mut header)?;
+                let zlib = matches!(header[..], [first, second] if is_zlib_header(first, second));
+                let reader = Cursor::new(header).chain(reader);
+                if zlib {
+                    Box::new(ZlibDecoder::new(reader))
+                } else {
+                    Box::new(DeflateDecoder::new(reader))
+                }
+            }
+            Compression::Brotli => Box::new(Decompressor::new(reader, BROTLI_BUFFER_SIZE)),
+            Compression::Zstd => Box::new(ZstdDecoder::new(reader)?),
+        })
+    }
+}
+
+/// Return a reader decompressing the given reader if its contents start with the magic number
+/// of a compression format, regardless of what its headers or file name say, along with the detected compression.
+pub(crate) fn decompress_detected<'a>(
+    mut reader: impl Read + 'a,
+) -> io::Result<(Box<dyn Read + 'a>, Option<Compression>)> {
+    let mut prefix = Vec::with_capacity(4);
+    (&mut reader).take(4).read_to_end(&mut prefix)?;
+    let compression = Compression::detect(&prefix);
+    let reader = Cursor::new(prefix).chain(reader);
+    match compression {
+        Some(compression) => Ok((compression.decoder(reader)?, Some(compression))),
+        None => Ok((Box::new(reader), None)),
+    }
+}
+
+/// Return whether the two bytes form a zlib header with the default window size and no preset dictionary,
+/// the only ones seen in practice. Being strict keeps plain text starting with `x` from being taken for zlib.
+fn is_zlib_header(first: u8, second: u8) -> bool {
+    first == 0x78 && second & 0x20 == 0 && (u16::from(first) << 8 | u16::from(second)) % 31 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_compression() {
+        assert_eq!(
+            Compression::from_content_encoding("GZIP"),
+            Ok(Some(Compression::Gzip))
+        );
+        assert_eq!(Compression::from_content_encoding("identity"), Ok(None));
+        assert!(Compression::from_content_encoding("compress").is_err());
+        assert_eq!(
+            Compression::from_extension("feed.xml.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(Compression::from_extension("feed.xml"), None);
+        assert_eq!(Compression::detect(b"<rss"), None);
+        assert_eq!(Compression::detect(b"\x78\x9c"), Some(Compression::Deflate));
+        assert_eq!(Compression::detect(b"x "), None);
+    }
+
+    #[test]
+    fn test_decompress_detected() {
+        let expected = fs::read("./resources/testing/example.rss").unwrap();
+        let mut contents = Vec::new();
+        let f = fs::File::open("./resources/testing/example.rss.gz").unwrap();
+        let (mut reader, compression) = decompress_detected(f).unwrap();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, expected);
+        assert_eq!(compression, Some(Compression::Gzip));
+
+        let mut contents = Vec::new();
+        let (mut reader, compression) = decompress_detected(expected.as_slice()).unwrap();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(contents, expected);
+        assert_eq!(compression, None);
+    }
+
+    #[test]
+    fn test_decoders() {
+        let expected = fs::read("./resources/testing/example.rss").unwrap();
+        let compressed = [
+            (Compression::Gzip, "example.rss.gz"),
+            (Compression::Brotli, "example.rss.br"),
+            (Compression::Zstd, "example.rss.zst"),
+            (Compression::Deflate, "example.rss.zz"),
+        ];
+        for (compression, name) in compressed {
+            let f = fs::File::open(format!("./resources/testing/{}", name)).unwrap();
+            let mut contents = Vec::new();
+            compression
+                .decoder(f)
+                .unwrap()
+                .read_to_end(&mut contents)
+                .unwrap();
+            assert_eq!(contents, expected, "{}", name);
+        }
+    }
+}
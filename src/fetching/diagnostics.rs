@@ -0,0 +1,324 @@
+//! Definition and implementation of the diagnostics recorded for every fetch.
+
+// std imports
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+// third-party imports
+use rss::Channel;
+
+// Local Imports
+use super::compression::Compression;
+use super::encoding::DetectedEncoding;
+use crate::processing::functions::item_id;
+
+/// The response headers recorded in the diagnostics, lowercase.
+const RECORDED_HEADERS: [&str; 9] = [
+    "content-type",
+    "content-encoding",
+    "content-length",
+    "cache-control",
+    "etag",
+    "last-modified",
+    "expires",
+    "age",
+    "retry-after",
+];
+
+/// The format a feed was detected as, from its root element.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+    /// RSS 1.0, with an `<rdf:RDF>` root element.
+    Rdf,
+    #[default]
+    Unknown,
+}
+
+/// Function implementations for FeedFormat.
+impl FeedFormat {
+    /// Detect the format of a feed from the start of its decoded contents, by whichever root element comes first.
+    pub fn detect(prefix: &str) -> FeedFormat {
+        let find = |root: &str| {
+            prefix
+                .match_indices(root)
+                .map(|(position, _)| position)
+                .find(|position| {
+                    prefix[position + root.len()..]
+                        .starts_with(|c: char| c == '>' || c == '/' || c.is_whitespace())
+                })
+        };
+        [
+            ("<rss", FeedFormat::Rss),
+            ("<feed", FeedFormat::Atom),
+            ("<rdf:RDF", FeedFormat::Rdf),
+        ]
+        .into_iter()
+        .filter_map(|(root, format)| find(root).map(|position| (position, format)))
+        .min_by_key(|(position, _)| *position)
+        .map(|(_, format)| format)
+        .unwrap_or_default()
+    }
+}
+
+/// How the items of a fetched channel compare to the previously stored version of the channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ItemCounts {
+    new: usize,
+    updated: usize,
+    unchanged: usize,
+}
+
+/// Function implementations for ItemCounts.
+impl ItemCounts {
+    /// Compare the items of the given channel to those of its previous version, matching them by `item_id`.
+    /// Without a previous version every item is new.
+    pub fn compare(previous: Option<&Channel>, channel: &Channel) -> ItemCounts {
+        let previous: HashMap<String, _> = previous
+            .map(|previous| previous.items())
+            .unwrap_or_default()
+            .iter()
+            .map(|item| (item_id(item), item))
+            .collect();
+        let mut counts = ItemCounts::default();
+        for item in channel.items() {
+            match previous.get(&item_id(item)) {
+                None => counts.new += 1,
+                Some(stored) if *stored != item => counts.updated += 1,
+                Some(_) => counts.unchanged += 1,
+            }
+        }
+        counts
+    }
+
+    /// Return the number of items which weren't in the previous version.
+    pub fn new_items(&self) -> usize {
+        self.new
+    }
+
+    /// Return the number of items which changed since the previous version.
+    pub fn updated(&self) -> usize {
+        self.updated
+    }
+
+    /// Return the number of items which didn't change since the previous version.
+    pub fn unchanged(&self) -> usize {
+        self.unchanged
+    }
+}
+
+/// What happened while fetching a feed, for finding out why a feed isn't updating.
+/// DNS and connect timings aren't exposed by the HTTP client and aren't recorded.
+#[derive(Debug, Clone)]
+pub struct Diagnostics {
+    url: String,
+    final_url: String,
+    status: Option<u16>,
+    headers: Vec<(String, String)>,
+    bytes_received: u64,
+    started: Instant,
+    time_to_first_byte: Option<Duration>,
+    total_time: Duration,
+    format: FeedFormat,
+    encoding: Option<DetectedEncoding>,
+    compression: Option<Compression>,
+    warnings: Vec<String>,
+    item_counts: Option<ItemCounts>,
+}
+
+/// Function implementations for Diagnostics.
+impl Diagnostics {
+    /// Start recording the diagnostics of a fetch of the given url.
+    pub(crate) fn new(url: &str) -> Diagnostics {
+        Diagnostics {
+            url: url.to_string(),
+            final_url: url.to_string(),
+            status: None,
+            headers: Vec::new(),
+            bytes_received: 0,
+            started: Instant::now(),
+            time_to_first_byte: None,
+            total_time: Duration::ZERO,
+            format: FeedFormat::Unknown,
+            encoding: None,
+            compression: None,
+            warnings: Vec::new(),
+            item_counts: None,
+        }
+    }
+
+    /// Return the url that was requested.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the url the feed was finally fetched from, after following redirects.
+    pub fn final_url(&self) -> &str {
+        &self.final_url
+    }
+
+    /// Return the HTTP status code of the final response, if the feed was fetched over HTTP.
+    pub fn status(&self) -> Option<u16> {
+        self.status
+    }
+
+    /// Return the recorded response headers, `Content-Type` and the caching headers, with lowercase names.
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    /// Return the value of the given recorded response header.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Return the number of body bytes received, before decompression when the client decompressed them.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Return how long it took until the response headers of the final request arrived.
+    pub fn time_to_first_byte(&self) -> Option<Duration> {
+        self.time_to_first_byte
+    }
+
+    /// Return how long the whole fetch took, including retries, redirects and parsing.
+    pub fn total_time(&self) -> Duration {
+        self.total_time
+    }
+
+    /// Return the format the feed was detected as.
+    pub fn format(&self) -> FeedFormat {
+        self.format
+    }
+
+    /// Return the character encoding the feed was decoded from.
+    pub fn encoding(&self) -> Option<DetectedEncoding> {
+        self.encoding
+    }
+
+    /// Return the compression the feed was decompressed from.
+    pub fn compression(&self) -> Option<Compression> {
+        self.compression
+    }
+
+    /// Return the problems noticed while reading and parsing the feed.
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    /// Return how the items compare to the previously stored version of the channel, once compared.
+    pub fn item_counts(&self) -> Option<ItemCounts> {
+        self.item_counts
+    }
+
+    pub(crate) fn set_final_url(&mut self, url: &str) {
+        self.final_url = url.to_string();
+    }
+
+    pub(crate) fn set_status(&mut self, status: u16) {
+        self.status = Some(status);
+    }
+
+    /// Record the headers of interest out of the given response headers, replacing those of an earlier response.
+    pub(crate) fn record_headers<'h>(
+        &mut self,
+        headers: impl IntoIterator<Item = (&'h str, &'h str)>,
+    ) {
+        self.headers.clear();
+        for (name, value) in headers {
+            let name = name.to_ascii_lowercase();
+            if RECORDED_HEADERS.contains(&name.as_str()) {
+                self.headers.push((name, value.to_string()));
+            }
+        }
+    }
+
+    pub(crate) fn set_bytes_received(&mut self, bytes: u64) {
+        self.bytes_received = bytes;
+    }
+
+    pub(crate) fn set_time_to_first_byte(&mut self, time: Duration) {
+        self.time_to_first_byte = Some(time);
+    }
+
+    pub(crate) fn set_format(&mut self, format: FeedFormat) {
+        self.format = format;
+    }
+
+    pub(crate) fn set_encoding(&mut self, encoding: DetectedEncoding) {
+        self.encoding = Some(encoding);
+    }
+
+    pub(crate) fn set_compression(&mut self, compression: Compression) {
+        self.compression = Some(compression);
+    }
+
+    pub(crate) fn warn(&mut self, warning: impl Into<String>) {
+        self.warnings.push(warning.into());
+    }
+
+    pub(crate) fn set_item_counts(&mut self, counts: ItemCounts) {
+        self.item_counts = Some(counts);
+    }
+
+    /// Stop the clock on the fetch.
+    pub(crate) fn finish(mut self) -> Diagnostics {
+        self.total_time = self.started.elapsed();
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rss::Item;
+
+    #[test]
+    fn test_feed_format() {
+        assert_eq!(
+            FeedFormat::detect("<?xml version=\"1.0\"?><rss version=\"2.0\">"),
+            FeedFormat::Rss
+        );
+        assert_eq!(
+            FeedFormat::detect("<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>rss</title>"),
+            FeedFormat::Atom
+        );
+        assert_eq!(FeedFormat::detect("<rdf:RDF>"), FeedFormat::Rdf);
+        assert_eq!(
+            FeedFormat::detect("<feedburner:info/>"),
+            FeedFormat::Unknown
+        );
+        assert_eq!(FeedFormat::detect("<html>"), FeedFormat::Unknown);
+    }
+
+    #[test]
+    fn test_item_counts() {
+        let item = |guid: &str, title: &str| {
+            let mut item = Item::default();
+            item.set_guid(rss::Guid {
+                value: guid.to_string(),
+                permalink: false,
+            });
+            item.set_title(title.to_string());
+            item
+        };
+        let mut previous = Channel::default();
+        previous.set_items(vec![item("a", "A"), item("b", "B")]);
+        let mut channel = Channel::default();
+        channel.set_items(vec![item("a", "A"), item("b", "B2"), item("c", "C")]);
+
+        let counts = ItemCounts::compare(Some(&previous), &channel);
+        assert_eq!(
+            (counts.new_items(), counts.updated(), counts.unchanged()),
+            (1, 1, 1)
+        );
+        assert_eq!(ItemCounts::compare(None, &channel).new_items(), 3);
+    }
+}
@@ -0,0 +1,299 @@
+//! Set of functions for discovering the feeds belonging to a website.
+
+// std imports
+use std::{error::Error, sync::OnceLock};
+
+// third-party imports
+use regex::Regex;
+use url::Url;
+
+// Local Imports
+use super::client::FeedClient;
+use super::config::FetchConfig;
+use super::diagnostics::FeedFormat;
+use super::fetcher::Fetcher;
+
+/// Paths probed when a page doesn't advertise any feeds.
+const COMMON_FEED_PATHS: [&str; 6] = [
+    "/feed",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.xml",
+    "/rss",
+];
+
+/// The format a discovered feed is advertised as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FeedKind {
+    /// An RSS feed, `application/rss+xml`.
+    Rss,
+    /// An Atom feed, `application/atom+xml`.
+    Atom,
+    /// A JSON feed, `application/feed+json`.
+    Json,
+}
+
+/// Where a discovered feed was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DiscoverySource {
+    /// The given url is a feed itself.
+    Direct,
+    /// The feed is advertised by a `<link rel="alternate">` on the page.
+    Link,
+    /// The feed was found by probing a common feed path.
+    Probe,
+}
+
+/// A feed found while discovering the feeds of a website.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedCandidate {
+    url: String,
+    title: Option<String>,
+    kind: Option<FeedKind>,
+    source: DiscoverySource,
+}
+
+/// Getters
+impl FeedCandidate {
+    /// Return the absolute url of the feed.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the title the feed is advertised with, if any.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Return the format of the feed, if known.
+    pub fn kind(&self) -> Option<FeedKind> {
+        self.kind
+    }
+
+    /// Return where the feed was found.
+    pub fn source(&self) -> DiscoverySource {
+        self.source
+    }
+
+    /// Return whether the feed looks like a comments feed, which are ranked below the main feeds.
+    fn is_comments(&self) -> bool {
+        let title = self.title.as_deref().unwrap_or_default().to_lowercase();
+        title.contains("comments") || self.url.to_lowercase().contains("comments")
+    }
+}
+
+/// Fetch the given url and return the feeds it links to, best candidates first.
+/// If the url is a feed itself it is returned as the only candidate,
+/// and if the page doesn't advertise any feeds the common feed paths of the site are probed.
+pub fn discover_feeds(url: &str) -> Result<Vec<FeedCandidate>, Box<dyn Error>> {
+    discover_feeds_with(&FeedClient::new(FetchConfig::default())?, url)
+}
+
+/// Same as `discover_feeds`, fetching with the given Fetcher.
+pub fn discover_feeds_with<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    url: &str,
+) -> Result<Vec<FeedCandidate>, Box<dyn Error>> {
+    let contents = fetcher.get(url)?;
+    if let Some(kind) = sniff_feed(&contents) {
+        return Ok(vec![FeedCandidate {
+            url: url.to_string(),
+            title: None,
+            kind: Some(kind),
+            source: DiscoverySource::Direct,
+        }]);
+    }
+
+    let mut candidates = find_feed_links(&contents, url)?;
+    if candidates.is_empty() {
+        let base = Url::parse(url)?;
+        for path in COMMON_FEED_PATHS {
+            let probe = base.join(path)?;
+            if let Ok(contents) = fetcher.get(probe.as_str()) {
+                if let Some(kind) = sniff_feed(&contents) {
+                    candidates.push(FeedCandidate {
+                        url: probe.to_string(),
+                        title: None,
+                        kind: Some(kind),
+                        source: DiscoverySource::Probe,
+                    });
+                }
+            }
+        }
+    }
+    rank(&mut candidates);
+    Ok(candidates)
+}
+
+/// Return the feeds advertised by `<link rel="alternate">` tags in the given HTML page,
+/// with their urls resolved against the page's `<base href>` or the given page url.
+pub fn find_feed_links(html: &str, page_url: &str) -> Result<Vec<FeedCandidate>, Box<dyn Error>> {
+    static BASE_RE: OnceLock<Regex> = OnceLock::new();
+    static LINK_RE: OnceLock<Regex> = OnceLock::new();
+    let mut base = Url::parse(page_url)?;
+    let base_re = BASE_RE.get_or_init(|| Regex::new(r"(?is)<base\b[^>]*>").unwrap());
+    if let Some(tag) = base_re.find(html) {
+        if let Some(href) = attribute(tag.as_str(), "href") {
+            if let Ok(url) = base.join(&href) {
+                base = url;
+            }
+        }
+    }
+
+    let link_re = LINK_RE.get_or_init(|| Regex::new(r"(?is)<link\b[^>]*>").unwrap());
+    let mut candidates: Vec<FeedCandidate> = Vec::new();
+    for tag in link_re.find_iter(html) {
+        let tag = tag.as_str();
+        let rel = attribute(tag, "rel").unwrap_or_default().to_lowercase();
+        if !rel.split_whitespace().any(|rel| rel == "alternate") {
+            continue;
+        }
+        let kind = match attribute(tag, "type").map(|t| t.trim().to_lowercase()) {
+            Some(t) if t == "application/rss+xml" => FeedKind::Rss,
+            Some(t) if t == "application/atom+xml" => FeedKind::Atom,
+            Some(t) if t == "application/feed+json" || t == "application/json" => FeedKind::Json,
+            _ => continue,
+        };
+        let href = match attribute(tag, "href") {
+            Some(href) if !href.trim().is_empty() => href,
+            _ => continue,
+        };
+        let url = match base.join(href.trim()) {
+            Ok(url) => url.to_string(),
+            Err(_) => continue,
+        };
+        if candidates.iter().any(|candidate| candidate.url == url) {
+            continue;
+        }
+        candidates.push(FeedCandidate {
+            url,
+            title: attribute(tag, "title").filter(|title| !title.trim().is_empty()),
+            kind: Some(kind),
+            source: DiscoverySource::Link,
+        });
+    }
+    Ok(candidates)
+}
+
+/// Return the format of the given contents if they look like a feed rather than a web page.
+/// XML feeds are recognized by their root element, the way `FeedFormat::detect` does.
+fn sniff_feed(contents: &str) -> Option<FeedKind> {
+    let start: String = contents.trim_start().chars().take(1024).collect();
+    if start.starts_with('{') && start.to_lowercase().contains("jsonfeed.org") {
+        return Some(FeedKind::Json);
+    }
+    match FeedFormat::detect(&start) {
+        FeedFormat::Rss | FeedFormat::Rdf => Some(FeedKind::Rss),
+        FeedFormat::Atom => Some(FeedKind::Atom),
+        FeedFormat::Unknown => None,
+    }
+}
+
+/// Sort the candidates so that the best ones come first.
+/// Direct feeds come before advertised ones, which come before probed ones,
+/// comment feeds come last and otherwise the order of appearance is kept.
+fn rank(candidates: &mut [FeedCandidate]) {
+    candidates.sort_by_key(|candidate| (candidate.is_comments(), candidate.source));
+}
+
+/// Return the decoded value of the given attribute of an HTML tag, matching its name ignoring case.
+fn attribute(tag: &str, name: &str) -> Option<String> {
+    static ATTRIBUTE_RE: OnceLock<Regex> = OnceLock::new();
+    let re = ATTRIBUTE_RE.get_or_init(|| {
+        Regex::new(r#"(?s)\s([^\s"'<>/=]+)\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+    });
+    let captures = re
+        .captures_iter(tag)
+        .find(|captures| captures[1].eq_ignore_ascii_case(name))?;
+    let value = captures
+        .get(2)
+        .or_else(|| captures.get(3))
+        .or_else(|| captures.get(4))?
+        .as_str();
+    Some(
+        value
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetching::fixture::FixtureFetcher;
+    use std::fs;
+
+    #[test]
+    /// Test wether find_feed_links() finds and resolves the advertised feeds
+    fn test_find_feed_links() {
+        let f = fs::read_to_string("./resources/testing/example.html").unwrap();
+        let mut candidates = find_feed_links(&f, "https://example.com/blog/post.html").unwrap();
+        rank(&mut candidates);
+        assert_eq!(candidates.len(), 4);
+
+        assert_eq!(candidates[0].url(), "https://example.com/blog/feed.xml");
+        assert_eq!(candidates[0].title(), Some("Example Blog"));
+        assert_eq!(candidates[0].kind(), Some(FeedKind::Rss));
+        assert_eq!(candidates[0].source(), DiscoverySource::Link);
+
+        assert_eq!(candidates[1].url(), "https://example.com/atom.xml?a=1&b=2");
+        assert_eq!(candidates[1].kind(), Some(FeedKind::Atom));
+        assert_eq!(candidates[2].url(), "https://cdn.example.com/feed.json");
+        assert_eq!(candidates[2].kind(), Some(FeedKind::Json));
+
+        // Comment feeds are ranked last
+        assert_eq!(candidates[3].title(), Some("Example Blog Comments"));
+    }
+
+    #[test]
+    /// Test wether sniff_feed() tells feeds apart from web pages
+    fn test_sniff_feed() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        assert_eq!(sniff_feed(&f), Some(FeedKind::Rss));
+        let f = fs::read_to_string("./resources/testing/example.html").unwrap();
+        assert_eq!(sniff_feed(&f), None);
+        assert_eq!(
+            sniff_feed("<?xml version=\"1.0\"?><feed xmlns=\"http://www.w3.org/2005/Atom\">"),
+            Some(FeedKind::Atom)
+        );
+        assert_eq!(
+            sniff_feed("<html><head><feedburner:info uri=\"example\"/></head></html>"),
+            None
+        );
+        assert_eq!(
+            sniff_feed("<rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">"),
+            Some(FeedKind::Rss)
+        );
+    }
+
+    #[test]
+    /// Test wether discover_feeds_with() recognizes feeds and probes the common feed paths of pages without feed links
+    fn test_discover_feeds_with() {
+        let feed = fs::read("./resources/testing/example.rss").unwrap();
+        let page =
+            b"<html><head><title>Example</title></head><body><p>No feeds here</p></body></html>";
+        let fetcher = FixtureFetcher::new()
+            .body("https://example.com/blog/main.rss", &feed, None)
+            .body("https://example.com/", page, Some("text/html"))
+            .body("https://example.com/feed", page, Some("text/html"))
+            .body("https://example.com/rss.xml", &feed, None);
+
+        let candidates =
+            discover_feeds_with(&fetcher, "https://example.com/blog/main.rss").unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].source(), DiscoverySource::Direct);
+        assert_eq!(candidates[0].kind(), Some(FeedKind::Rss));
+
+        let candidates = discover_feeds_with(&fetcher, "https://example.com/").unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].url(), "https://example.com/rss.xml");
+        assert_eq!(candidates[0].source(), DiscoverySource::Probe);
+
+        // Every common path is probed, missing ones respond with 404
+        assert_eq!(fetcher.requests().len(), 2 + COMMON_FEED_PATHS.len());
+    }
+}
@@ -0,0 +1,176 @@
+//! Set of functions for detecting the character encoding of feed bodies.
+
+// std imports
+use std::sync::OnceLock;
+
+// third-party imports
+use encoding_rs::{Encoding, UTF_8};
+use regex::{bytes, Regex};
+
+/// Where the character encoding of a body was determined from, in order of precedence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncodingSource {
+    /// A byte order mark at the start of the body.
+    Bom,
+    /// The `charset` parameter of the HTTP `Content-Type` header.
+    ContentType,
+    /// The `encoding` of the XML declaration.
+    XmlDeclaration,
+    /// The `charset` of an HTML `<meta>` tag.
+    HtmlMeta,
+    /// Nothing declared an encoding, so UTF-8 is assumed.
+    Default,
+}
+
+/// The detected character encoding of a body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DetectedEncoding {
+    encoding: &'static Encoding,
+    source: EncodingSource,
+    declared: Option<&'static Encoding>,
+}
+
+/// Function implementations for DetectedEncoding.
+impl DetectedEncoding {
+    /// Return the name of the encoding, e.g. `Shift_JIS`.
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Return where the encoding was determined from.
+    pub fn source(&self) -> EncodingSource {
+        self.source
+    }
+
+    /// Return the encoding.
+    pub(crate) fn encoding(&self) -> &'static Encoding {
+        self.encoding
+    }
+
+    /// Return whether the body can be handed to the XML parser as it is,
+    /// which is the case for UTF-8 bodies which don't declare another encoding.
+    pub(crate) fn is_plain_utf8(&self) -> bool {
+        self.encoding == UTF_8
+            && self.source != EncodingSource::Bom
+            && self.declared.is_none_or(|declared| declared == UTF_8)
+    }
+}
+
+/// Detect the encoding of a body from its first bytes and the HTTP `Content-Type` header.
+/// The byte order mark takes precedence over the header, which takes precedence over the XML declaration.
+pub fn detect_encoding(prefix: &[u8], content_type: Option<&str>) -> DetectedEncoding {
+    let declared = xml_declared_encoding(prefix);
+    let (encoding, source) = if let Some((encoding, _)) = Encoding::for_bom(prefix) {
+        (encoding, EncodingSource::Bom)
+    } else if let Some(encoding) = content_type.and_then(charset) {
+        (encoding, EncodingSource::ContentType)
+    } else if let Some(encoding) = declared {
+        (encoding, EncodingSource::XmlDeclaration)
+    } else if let Some(encoding) = html_meta_encoding(prefix) {
+        (encoding, EncodingSource::HtmlMeta)
+    } else {
+        (UTF_8, EncodingSource::Default)
+    };
+    DetectedEncoding {
+        encoding,
+        source,
+        declared,
+    }
+}
+
+/// Transcode the given body to UTF-8 using the detected encoding.
+/// The encoding of the XML declaration is rewritten to UTF-8 so the parser doesn't decode the text a second time.
+pub fn decode(bytes: &[u8], detected: &DetectedEncoding) -> String {
+    let (contents, _, _) = detected.encoding().decode(bytes);
+    if detected.declared.is_some() {
+        static DECLARATION_RE: OnceLock<Regex> = OnceLock::new();
+        let re = DECLARATION_RE.get_or_init(|| {
+            Regex::new(r#"^(\s*<\?xml[^>]*?encoding\s*=\s*)["'][^"']*["']"#).unwrap()
+        });
+        re.replace(&contents, "${1}\"UTF-8\"").into_owned()
+    } else {
+        contents.into_owned()
+    }
+}
+
+/// Return the encoding named by the `charset` parameter of a `Content-Type` header.
+fn charset(content_type: &str) -> Option<&'static Encoding> {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .and_then(|(_, value)| Encoding::for_label(value.trim().trim_matches('"').as_bytes()))
+}
+
+/// Return the encoding named by the XML declaration at the start of the body.
+fn xml_declared_encoding(prefix: &[u8]) -> Option<&'static Encoding> {
+    static ENCODING_RE: OnceLock<bytes::Regex> = OnceLock::new();
+    let re = ENCODING_RE.get_or_init(|| {
+        bytes::Regex::new(r#"^\s*<\?xml[^>]*?encoding\s*=\s*["']([^"']+)["']"#).unwrap()
+    });
+    let label = re.captures(prefix)?.get(1)?.as_bytes();
+    match Encoding::for_label(label) {
+        // A UTF-16 declaration in a body without a BOM was already decoded as ASCII, so it can't be right
+        Some(encoding) if encoding.output_encoding() != encoding => None,
+        encoding => encoding,
+    }
+}
+
+/// Return the encoding named by an HTML `<meta charset>` or `<meta http-equiv>` tag.
+fn html_meta_encoding(prefix: &[u8]) -> Option<&'static Encoding> {
+    static CHARSET_RE: OnceLock<bytes::Regex> = OnceLock::new();
+    let re = CHARSET_RE.get_or_init(|| {
+        bytes::Regex::new(r#"(?i)<meta[^>]+charset\s*=\s*["']?([a-z0-9_\-:.]+)"#).unwrap()
+    });
+    let label = re.captures(prefix)?.get(1)?.as_bytes();
+    Encoding::for_label(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    /// Test wether detect_encoding() applies BOM, header and declaration in the right order
+    fn test_detect_encoding_precedence() {
+        let declared = b"<?xml version=\"1.0\" encoding=\"Shift_JIS\"?><rss/>";
+        let detected = detect_encoding(declared, None);
+        assert_eq!(detected.name(), "Shift_JIS");
+        assert_eq!(detected.source(), EncodingSource::XmlDeclaration);
+
+        let detected = detect_encoding(declared, Some("application/rss+xml; charset=windows-1252"));
+        assert_eq!(detected.name(), "windows-1252");
+        assert_eq!(detected.source(), EncodingSource::ContentType);
+
+        let mut bom = b"\xEF\xBB\xBF".to_vec();
+        bom.extend_from_slice(declared);
+        let detected = detect_encoding(&bom, Some("text/xml; charset=windows-1252"));
+        assert_eq!(detected.name(), "UTF-8");
+        assert_eq!(detected.source(), EncodingSource::Bom);
+
+        let detected = detect_encoding(b"<rss/>", Some("text/xml"));
+        assert_eq!(detected.source(), EncodingSource::Default);
+        assert!(detected.is_plain_utf8());
+
+        let detected = detect_encoding(b"<html><meta charset=\"iso-8859-1\">", None);
+        assert_eq!(detected.name(), "windows-1252");
+        assert_eq!(detected.source(), EncodingSource::HtmlMeta);
+    }
+
+    #[test]
+    /// Test wether decode() transcodes the fixtures and rewrites their declaration
+    fn test_decode() {
+        let bytes = fs::read("./resources/testing/example_shift_jis.rss").unwrap();
+        let detected = detect_encoding(&bytes, None);
+        let contents = decode(&bytes, &detected);
+        assert!(contents.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+        assert!(contents.contains("日本語のフィード"));
+
+        let bytes = fs::read("./resources/testing/example_windows_1252.rss").unwrap();
+        let detected = detect_encoding(&bytes, Some("text/xml; charset=windows-1252"));
+        let contents = decode(&bytes, &detected);
+        assert!(contents.contains("Café “quoted” – dash"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Definition and implementation of the fetch error.
+
+// std imports
+use std::{error::Error, fmt, time::Duration};
+
+/// An error encountered while fetching a feed over HTTP.
+/// Returned boxed as `Box<dyn Error>`, use `downcast_ref::<FetchError>()` to inspect it.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The server responded with an unsuccessful status code.
+    /// `url` is the url which responded, `requested_url` the one requested before any redirects were followed.
+    Status {
+        url: String,
+        requested_url: String,
+        status: u16,
+        retry_after: Option<Duration>,
+    },
+    /// The request failed before a response was received, or while its body was being read.
+    Transport {
+        url: String,
+        message: String,
+        retryable: bool,
+    },
+    /// The response body is larger than the configured limit.
+    BodyTooLarge { url: String, limit: u64 },
+    /// The host asked to be left alone for longer than the configured maximum wait.
+    RateLimited { url: String, retry_after: Duration },
+    /// The robots.txt of the host disallows fetching the url.
+    Disallowed { url: String },
+    /// The url isn't an `http://` or `https://` URL.
+    UnsupportedScheme { url: String },
+}
+
+/// Function implementations for FetchError.
+impl FetchError {
+    /// Create a FetchError from a failed reqwest request.
+    pub(crate) fn from_reqwest(url: &str, error: reqwest::Error) -> FetchError {
+        FetchError::Transport {
+            url: url.to_string(),
+            retryable: error.is_timeout() || error.is_connect() || error.is_body(),
+            message: error.without_url().to_string(),
+        }
+    }
+
+    /// Return the url that was being fetched.
+    pub fn url(&self) -> &str {
+        match self {
+            FetchError::Status { url, .. }
+            | FetchError::Transport { url, .. }
+            | FetchError::BodyTooLarge { url, .. }
+            | FetchError::RateLimited { url, .. }
+            | FetchError::Disallowed { url }
+            | FetchError::UnsupportedScheme { url } => url,
+        }
+    }
+
+    /// Return the url that was requested before any redirects were followed.
+    /// Only differs from `url()` for unsuccessful status codes reached through redirects.
+    pub fn requested_url(&self) -> &str {
+        match self {
+            FetchError::Status { requested_url, .. } => requested_url,
+            _ => self.url(),
+        }
+    }
+
+    /// Return the HTTP status code, if the server responded.
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            FetchError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+
+    /// Return how long the server asked to wait before retrying, if it did.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            FetchError::Status { retry_after, .. } => *retry_after,
+            FetchError::RateLimited { retry_after, .. } => Some(*retry_after),
+            _ => None,
+        }
+    }
+
+    /// Return whether the request may succeed when retried.
+    /// Timeouts, connection failures, 408, 429 and 5xx responses other than 501 are retryable.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::Status { status, .. } => {
+                matches!(status, 408 | 429) || (*status >= 500 && *status != 501)
+            }
+            FetchError::Transport { retryable, .. } => *retryable,
+            FetchError::BodyTooLarge { .. }
+            | FetchError::RateLimited { .. }
+            | FetchError::Disallowed { .. }
+            | FetchError::UnsupportedScheme { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Status { url, status, .. } => {
+                write!(f, "{} responded with HTTP {}", url, status)
+            }
+            FetchError::Transport { url, message, .. } => {
+                write!(f, "failed to fetch {}: {}", url, message)
+            }
+            FetchError::BodyTooLarge { url, limit } => {
+                write!(f, "{} responded with more than {} bytes", url, limit)
+            }
+            FetchError::RateLimited { url, retry_after } => write!(
+                f,
+                "{} asked to wait {} seconds before fetching again",
+                url,
+                retry_after.as_secs()
+            ),
+            FetchError::Disallowed { url } => {
+                write!(f, "{} is disallowed by robots.txt", url)
+            }
+            FetchError::UnsupportedScheme { url } => {
+                write!(f, "{} is not an http or https URL", url)
+            }
+        }
+    }
+}
+
+impl Error for FetchError {}
@@ -0,0 +1,91 @@
+//! Definition and implementation of the result of fetching a feed.
+
+// third-party imports
+use rss::Channel;
+
+// Local Imports
+use super::diagnostics::{Diagnostics, ItemCounts};
+use super::redirect::{Redirect, SubscriptionUpdate};
+
+/// A fetched and parsed feed, along with the redirects followed to get it and the diagnostics of the fetch.
+#[derive(Debug, Clone)]
+pub struct FetchedChannel {
+    channel: Channel,
+    url: String,
+    redirects: Vec<Redirect>,
+    diagnostics: Diagnostics,
+}
+
+/// Function implementations for FetchedChannel.
+impl FetchedChannel {
+    /// Create a new FetchedChannel, fetched from the given url through the given redirects.
+    pub fn new(channel: Channel, url: &str, redirects: Vec<Redirect>) -> FetchedChannel {
+        let mut diagnostics = Diagnostics::new(url);
+        if let Some(redirect) = redirects.last() {
+            diagnostics.set_final_url(redirect.to());
+        }
+        FetchedChannel {
+            channel,
+            url: url.to_string(),
+            redirects,
+            diagnostics: diagnostics.finish(),
+        }
+    }
+
+    /// Set the diagnostics recorded while fetching the channel.
+    pub(crate) fn with_diagnostics(mut self, diagnostics: Diagnostics) -> FetchedChannel {
+        self.diagnostics = diagnostics;
+        self
+    }
+
+    /// Return the parsed channel.
+    pub fn channel(&self) -> &Channel {
+        &self.channel
+    }
+
+    /// Return the parsed channel, dropping the rest.
+    pub fn into_channel(self) -> Channel {
+        self.channel
+    }
+
+    /// Return the url that was requested.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the url the feed was finally fetched from, after following redirects.
+    pub fn final_url(&self) -> &str {
+        self.redirects
+            .last()
+            .map(|redirect| redirect.to())
+            .unwrap_or(&self.url)
+    }
+
+    /// Return the redirects followed, in order.
+    pub fn redirects(&self) -> &[Redirect] {
+        &self.redirects
+    }
+
+    /// Return whether the feed was fetched through redirects which were all permanent.
+    pub fn is_permanently_redirected(&self) -> bool {
+        !self.redirects.is_empty() && self.redirects.iter().all(Redirect::is_permanent)
+    }
+
+    /// Return the diagnostics of the fetch.
+    pub fn diagnostics(&self) -> &Diagnostics {
+        &self.diagnostics
+    }
+
+    /// Count the new, updated and unchanged items against the previously stored version of the channel,
+    /// recording them in the diagnostics.
+    pub fn compare_with(&mut self, previous: Option<&Channel>) -> ItemCounts {
+        let counts = ItemCounts::compare(previous, &self.channel);
+        self.diagnostics.set_item_counts(counts);
+        counts
+    }
+
+    /// Return the change to the subscription suggested by the redirects, if any were permanent.
+    pub fn subscription_update(&self) -> Option<SubscriptionUpdate> {
+        SubscriptionUpdate::from_redirects(&self.url, &self.redirects)
+    }
+}
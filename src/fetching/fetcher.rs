@@ -0,0 +1,140 @@
+//! Definition of the Fetcher trait, the transport the fetching pipeline is generic over.
+
+// std imports
+use std::{error::Error, io::Read};
+
+// third-party imports
+use chrono::prelude::*;
+use rss::Channel;
+
+// Local Imports
+use super::body::{ByteCount, CountingReader};
+use super::compression::decompress_detected;
+use super::diagnostics::Diagnostics;
+use super::encoding::{decode, detect_encoding};
+use super::fetched::FetchedChannel;
+use super::functions::read_channel_diagnosed;
+use super::health::HealthTracker;
+use super::redirect::Redirect;
+
+/// The raw response to a fetch: a reader over its body, its content type and the redirects followed to get it.
+pub struct FetchResponse<'a> {
+    body: Box<dyn Read + 'a>,
+    content_type: Option<String>,
+    redirects: Vec<Redirect>,
+    diagnostics: Option<Diagnostics>,
+}
+
+/// Function implementations for FetchResponse.
+impl<'a> FetchResponse<'a> {
+    /// Create a new FetchResponse reading the given body.
+    pub fn new(body: impl Read + 'a) -> FetchResponse<'a> {
+        FetchResponse {
+            body: Box::new(body),
+            content_type: None,
+            redirects: Vec::new(),
+            diagnostics: None,
+        }
+    }
+
+    /// Set the `Content-Type` of the body.
+    pub fn content_type(mut self, content_type: Option<&str>) -> FetchResponse<'a> {
+        self.content_type = content_type.map(str::to_string);
+        self
+    }
+
+    /// Set the redirects followed to get the response.
+    pub fn redirects(mut self, redirects: Vec<Redirect>) -> FetchResponse<'a> {
+        self.redirects = redirects;
+        self
+    }
+
+    /// Set the diagnostics recorded while fetching the response, to be completed while it is parsed.
+    pub(crate) fn diagnostics(mut self, diagnostics: Diagnostics) -> FetchResponse<'a> {
+        self.diagnostics = Some(diagnostics);
+        self
+    }
+
+    /// Split the response into its body, content type and redirects.
+    pub fn into_parts(self) -> (Box<dyn Read + 'a>, Option<String>, Vec<Redirect>) {
+        (self.body, self.content_type, self.redirects)
+    }
+}
+
+/// A transport feeds are fetched over.
+/// Only `fetch` needs to be implemented, parsing and health tracking are built on top of it.
+pub trait Fetcher {
+    /// Fetch the given url, returning a reader over its body.
+    fn fetch(&self, url: &str) -> Result<FetchResponse<'_>, Box<dyn Error>>;
+
+    /// Fetch the contents from the given url, transcoded to UTF-8 from its detected encoding.
+    fn get(&self, url: &str) -> Result<String, Box<dyn Error>> {
+        read_text(self.fetch(url)?)
+    }
+
+    /// Fetch the feed at the given url, reporting the redirects followed along with the channel.
+    fn fetch_channel(&self, url: &str) -> Result<FetchedChannel, Box<dyn Error>> {
+        read_fetched_channel(url, self.fetch(url)?)
+    }
+
+    /// Fetch the feeds at the given urls, reporting the redirects followed along with every channel.
+    fn fetch_channels(&self, urls: &[&str]) -> Vec<Result<FetchedChannel, Box<dyn Error>>> {
+        urls.iter().map(|url| self.fetch_channel(url)).collect()
+    }
+
+    /// Fetch the feeds at the given urls and parse them. Returning a vector of channels.
+    fn get_channels(&self, urls: &[&str]) -> Vec<Result<Channel, Box<dyn Error>>> {
+        urls.iter()
+            .map(|url| Ok(self.fetch_channel(url)?.into_channel()))
+            .collect()
+    }
+
+    /// Same as `get_channels`, recording the outcome of every fetch in the given HealthTracker.
+    fn get_channels_tracked(
+        &self,
+        urls: &[&str],
+        tracker: &mut HealthTracker,
+    ) -> Vec<Result<Channel, Box<dyn Error>>> {
+        let channels = self.get_channels(urls);
+        for (url, channel) in urls.iter().zip(&channels) {
+            match channel {
+                Ok(_) => tracker.record_success(url, Utc::now()),
+                Err(e) => tracker.record_failure(url, &e.to_string(), Utc::now()),
+            }
+        }
+        channels
+    }
+}
+
+/// Read the body of the given response, decompressed if needed and transcoded to UTF-8 from its detected encoding.
+pub(crate) fn read_text(response: FetchResponse<'_>) -> Result<String, Box<dyn Error>> {
+    let (body, content_type, _) = response.into_parts();
+    let mut contents = Vec::new();
+    decompress_detected(body)?.0.read_to_end(&mut contents)?;
+    let prefix = &contents[..contents.len().min(1024)];
+    let detected = detect_encoding(prefix, content_type.as_deref());
+    Ok(decode(&contents, &detected))
+}
+
+/// Parse the body of the given response, fetched from the given url, completing its diagnostics.
+pub(crate) fn read_fetched_channel(
+    url: &str,
+    response: FetchResponse<'_>,
+) -> Result<FetchedChannel, Box<dyn Error>> {
+    let mut diagnostics = response
+        .diagnostics
+        .unwrap_or_else(|| Diagnostics::new(url));
+    if let Some(redirect) = response.redirects.last() {
+        diagnostics.set_final_url(redirect.to());
+    }
+    let received = ByteCount::default();
+    let body = CountingReader::new(response.body, received.clone());
+    let channel = read_channel_diagnosed(body, response.content_type.as_deref(), &mut diagnostics)?;
+    if diagnostics.bytes_received() == 0 {
+        diagnostics.set_bytes_received(received.get());
+    }
+    Ok(
+        FetchedChannel::new(channel, url, response.redirects)
+            .with_diagnostics(diagnostics.finish()),
+    )
+}
@@ -0,0 +1,197 @@
+//! Definition and implementation of the in-memory fetcher, serving fixtures instead of going over the network.
+
+// std imports
+use std::{collections::HashMap, error::Error, fs, io, path::Path, sync::Mutex};
+
+// Local Imports
+use super::diagnostics::Diagnostics;
+use super::error::FetchError;
+use super::fetcher::{FetchResponse, Fetcher};
+use super::redirect::Redirect;
+
+/// The maximum number of redirects a FixtureFetcher follows.
+const MAX_FIXTURE_REDIRECTS: usize = 10;
+
+/// A canned response served by a FixtureFetcher.
+#[derive(Debug, Clone)]
+enum Fixture {
+    Body {
+        contents: Vec<u8>,
+        content_type: Option<String>,
+    },
+    Status(u16),
+    Redirect {
+        status: u16,
+        location: String,
+    },
+}
+
+/// A Fetcher serving canned responses from memory, for tests and offline use.
+/// Urls without a fixture respond with 404 Not Found.
+#[derive(Debug, Default)]
+pub struct FixtureFetcher {
+    fixtures: HashMap<String, Fixture>,
+    requests: Mutex<Vec<String>>,
+}
+
+/// Function implementations for FixtureFetcher.
+impl FixtureFetcher {
+    /// Create a new FixtureFetcher without any fixtures.
+    pub fn new() -> FixtureFetcher {
+        FixtureFetcher::default()
+    }
+
+    /// Serve the given body for the given url.
+    pub fn body(
+        mut self,
+        url: &str,
+        contents: &[u8],
+        content_type: Option<&str>,
+    ) -> FixtureFetcher {
+        self.fixtures.insert(
+            url.to_string(),
+            Fixture::Body {
+                contents: contents.to_vec(),
+                content_type: content_type.map(str::to_string),
+            },
+        );
+        self
+    }
+
+    /// Serve the contents of the given file for the given url.
+    pub fn file(self, url: &str, path: impl AsRef<Path>) -> io::Result<FixtureFetcher> {
+        let contents = fs::read(path)?;
+        Ok(self.body(url, &contents, None))
+    }
+
+    /// Respond to the given url with the given unsuccessful status code.
+    pub fn status(mut self, url: &str, status: u16) -> FixtureFetcher {
+        self.fixtures
+            .insert(url.to_string(), Fixture::Status(status));
+        self
+    }
+
+    /// Redirect the given url to the given location with the given status code.
+    pub fn redirect(mut self, url: &str, status: u16, location: &str) -> FixtureFetcher {
+        self.fixtures.insert(
+            url.to_string(),
+            Fixture::Redirect {
+                status,
+                location: location.to_string(),
+            },
+        );
+        self
+    }
+
+    /// Return the urls requested so far, in order, including redirect targets.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+impl Fetcher for FixtureFetcher {
+    fn fetch(&self, url: &str) -> Result<FetchResponse<'_>, Box<dyn Error>> {
+        let mut redirects = Vec::new();
+        let mut current = url.to_string();
+        loop {
+            self.requests
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(current.clone());
+            match self.fixtures.get(&current) {
+                Some(Fixture::Body {
+                    contents,
+                    content_type,
+                }) => {
+                    let mut diagnostics = Diagnostics::new(url);
+                    diagnostics.set_status(200);
+                    if let Some(content_type) = content_type {
+                        diagnostics.record_headers([("content-type", content_type.as_str())]);
+                    }
+                    return Ok(FetchResponse::new(contents.as_slice())
+                        .content_type(content_type.as_deref())
+                        .redirects(redirects)
+                        .diagnostics(diagnostics));
+                }
+                Some(Fixture::Redirect { status, location })
+                    if redirects.len() < MAX_FIXTURE_REDIRECTS =>
+                {
+                    redirects.push(Redirect::new(*status, &current, location));
+                    current = location.clone();
+                }
+                Some(Fixture::Redirect { .. }) => {
+                    return Err(Box::new(FetchError::Transport {
+                        url: url.to_string(),
+                        message: format!("more than {} redirects", MAX_FIXTURE_REDIRECTS),
+                        retryable: false,
+                    }))
+                }
+                Some(Fixture::Status(status)) => {
+                    return Err(Box::new(FetchError::Status {
+                        url: current,
+                        requested_url: url.to_string(),
+                        status: *status,
+                        retry_after: None,
+                    }))
+                }
+                None => {
+                    return Err(Box::new(FetchError::Status {
+                        url: current,
+                        requested_url: url.to_string(),
+                        status: 404,
+                        retry_after: None,
+                    }))
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetching::health::HealthTracker;
+    use crate::fetching::redirect::SubscriptionUpdate;
+
+    #[test]
+    fn test_fixture_fetcher() {
+        let fetcher = FixtureFetcher::new()
+            .file("https://example.com/rss", "./resources/testing/example.rss")
+            .unwrap()
+            .redirect("http://example.com/rss", 301, "https://example.com/rss")
+            .status("https://example.com/gone", 410);
+        let mut tracker = HealthTracker::new();
+
+        let urls = [
+            "http://example.com/rss",
+            "https://example.com/gone",
+            "https://example.com/missing",
+        ];
+        let results = fetcher.get_channels_tracked(&urls, &mut tracker);
+        assert_eq!(results[0].as_ref().unwrap().items().len(), 3);
+        assert!(results[1].is_err());
+        assert_eq!(tracker.unhealthy().len(), 0);
+        assert_eq!(tracker.health(urls[2]).consecutive_failures(), 1);
+        assert_eq!(
+            fetcher.requests(),
+            [
+                "http://example.com/rss",
+                "https://example.com/rss",
+                "https://example.com/gone",
+                "https://example.com/missing"
+            ]
+        );
+
+        let fetched = fetcher.fetch_channel(urls[0]).unwrap();
+        assert_eq!(fetched.final_url(), "https://example.com/rss");
+        assert!(fetched.subscription_update().is_some());
+        let error = fetcher.fetch_channel(urls[1]).unwrap_err();
+        assert!(matches!(
+            SubscriptionUpdate::from_error(error.as_ref()),
+            Some(SubscriptionUpdate::Gone { .. })
+        ));
+    }
+}
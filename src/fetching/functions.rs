@@ -1,26 +1,171 @@
 //! Set of functions for fetching and parsing rss feeds.
 
 // std imports
-use std::{error::Error, io::BufReader};
+use std::{
+    error::Error,
+    io::{BufRead, BufReader, Cursor, Read},
+    sync::OnceLock,
+};
 
 // third-party imports
 use regex::Regex;
 use rss::Channel;
 
-/// Fetch the contents from the given URL.
-fn get(uri: &str) -> Result<String, Box<dyn Error>> {
-    let body = reqwest::blocking::get(uri)?.text()?;
-    Ok(body)
-}
+// Local Imports
+use super::body::RecordingReader;
+use super::client::FeedClient;
+use super::compression::decompress_detected;
+use super::config::FetchConfig;
+use super::diagnostics::{Diagnostics, FeedFormat};
+use super::encoding::{decode, detect_encoding};
+use super::fetcher::Fetcher;
+use super::recovery::recover;
+use crate::processing::namespaces::normalize_namespaces;
+use crate::processing::urls::resolve_urls;
+
+/// The largest part of a streamed feed kept to repair it from if the parser rejects it, 4 MiB.
+/// Feeds which fail to parse further in aren't repaired, rather than holding every feed in memory twice.
+const MAX_RECOVERED_SIZE: usize = 4 * 1024 * 1024;
 
 /// Parse the RSS feed from the given url.
-fn parse_rss(contents: &str) -> Result<Channel, Box<dyn Error>> {
-    let channel = Channel::read_from(BufReader::new(contents.as_bytes()))?;
+pub(crate) fn parse_rss(contents: &str) -> Result<Channel, Box<dyn Error>> {
+    parse_rss_from(BufReader::new(contents.as_bytes()))
+}
+
+/// Parse the RSS feed from the given reader.
+pub(crate) fn parse_rss_from<R: BufRead>(reader: R) -> Result<Channel, Box<dyn Error>> {
+    let channel = Channel::read_from(reader)?;
+    Ok(channel)
+}
+
+/// Read a feed from the given reader and parse it, decompressing it first if it starts with the magic number of a compression format.
+/// The character encoding is detected from the byte order mark, the given `Content-Type` header and the XML declaration, in that order.
+/// UTF-8 RSS feeds are parsed while they are being read, feeds which need to be transcoded or sanitized are read into memory first.
+/// Malformed feeds the parser rejects are repaired and parsed again.
+/// Extensions from well-known namespaces are stored under their usual prefix, see `normalize_namespaces`.
+/// Relative urls are resolved against the document's `xml:base` and the channel link.
+pub(crate) fn read_channel<R: Read>(
+    reader: R,
+    content_type: Option<&str>,
+) -> Result<Channel, Box<dyn Error>> {
+    read_channel_diagnosed(reader, content_type, &mut Diagnostics::new(""))
+}
+
+/// Same as `read_channel`, recording the detected compression, encoding and format in the given Diagnostics,
+/// along with warnings describing what was repaired. Relative urls are also resolved against the final url of the Diagnostics.
+pub(crate) fn read_channel_diagnosed<R: Read>(
+    reader: R,
+    content_type: Option<&str>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Channel, Box<dyn Error>> {
+    let (reader, compression) = decompress_detected(reader)?;
+    if let Some(compression) = compression {
+        diagnostics.set_compression(compression);
+    }
+    read_decompressed_channel(reader, content_type, diagnostics)
+}
+
+/// Same as `read_channel_diagnosed` for a reader which is already decompressed,
+/// so a size limit applied to the reader applies to the decompressed feed.
+pub(crate) fn read_decompressed_channel<R: Read>(
+    mut reader: R,
+    content_type: Option<&str>,
+    diagnostics: &mut Diagnostics,
+) -> Result<Channel, Box<dyn Error>> {
+    let mut prefix = Vec::new();
+    (&mut reader).take(1024).read_to_end(&mut prefix)?;
+    let detected = detect_encoding(&prefix, content_type);
+    diagnostics.set_encoding(detected);
+    let xml_base = find_xml_base(&String::from_utf8_lossy(&prefix));
+    let format = FeedFormat::detect(&String::from_utf8_lossy(&prefix));
+    diagnostics.set_format(format);
+    if format == FeedFormat::Unknown {
+        diagnostics.warn("no <rss>, <feed> or <rdf:RDF> root element in the first 1024 bytes");
+    }
+    if let Some(content_type) = content_type.filter(|c| c.to_lowercase().contains("html")) {
+        diagnostics.warn(format!(
+            "served as {}, which isn't a feed type",
+            content_type
+        ));
+    }
+    let needs_sanitizing = format == FeedFormat::Atom;
+    if needs_sanitizing {
+        diagnostics
+            .warn("Atom feed converted to RSS, elements without an RSS equivalent are dropped");
+    }
+    let reader = Cursor::new(prefix).chain(reader);
+    let mut channel = if needs_sanitizing || !detected.is_plain_utf8() {
+        let mut contents = Vec::new();
+        BufReader::new(reader).read_to_end(&mut contents)?;
+        let contents = decode(&contents, &detected);
+        if needs_sanitizing {
+            parse_rss_recovering(&sanitize(&contents), diagnostics)?
+        } else {
+            parse_rss_recovering(&contents, diagnostics)?
+        }
+    } else {
+        let mut recorder = RecordingReader::new(reader, MAX_RECOVERED_SIZE);
+        match parse_rss_from(BufReader::new(&mut recorder)) {
+            Ok(channel) => channel,
+            Err(error) => {
+                let (contents, mut rest) = recorder.into_parts();
+                let mut contents = match contents {
+                    Some(contents) => contents,
+                    None => return Err(error),
+                };
+                if rest.read_to_end(&mut contents).is_err() {
+                    return Err(error);
+                }
+                parse_rss_recovering(&String::from_utf8_lossy(&contents), diagnostics)?
+            }
+        }
+    };
+    normalize_namespaces(&mut channel);
+    let fetch_url = Some(diagnostics.final_url()).filter(|url| !url.is_empty());
+    resolve_urls(&mut channel, xml_base.as_deref(), fetch_url);
+    Ok(channel)
+}
+
+/// Return the `xml:base` of the root or channel element at the start of a feed.
+/// The parser drops attributes, so `xml:base` on individual items isn't supported.
+fn find_xml_base(prefix: &str) -> Option<String> {
+    static BASE_RE: OnceLock<Regex> = OnceLock::new();
+    let base_re = BASE_RE.get_or_init(|| {
+        Regex::new(
+            r#"<(?:rss|feed|channel|rdf:RDF)\b[^>]*\sxml:base\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+        )
+        .unwrap()
+    });
+    let captures = base_re.captures(prefix)?;
+    captures
+        .get(1)
+        .or_else(|| captures.get(2))
+        .map(|base| base.as_str().to_string())
+}
+
+/// Parse the given RSS feed, repairing it and parsing it again if the parser rejects it.
+/// What was repaired is recorded as warnings in the given Diagnostics, the original error is returned if repairing doesn't help.
+fn parse_rss_recovering(
+    contents: &str,
+    diagnostics: &mut Diagnostics,
+) -> Result<Channel, Box<dyn Error>> {
+    let error = match parse_rss(contents) {
+        Ok(channel) => return Ok(channel),
+        Err(e) => e,
+    };
+    let (recovered, warnings) = recover(contents);
+    if warnings.is_empty() {
+        return Err(error);
+    }
+    let channel = parse_rss(&recovered).map_err(|_| error)?;
+    for warning in warnings {
+        diagnostics.warn(warning);
+    }
     Ok(channel)
 }
 
 /// Transforms feed tags into rss tags and wraps them around a channel tag in an attempt to improve the success rate of the rss parser
-fn sanitize(contents: &str) -> String {
+pub(crate) fn sanitize(contents: &str) -> String {
     let start_re = Regex::new("<feed (.*?)>").unwrap();
     let end_re = Regex::new("</feed>").unwrap();
     if start_re.is_match(contents) && end_re.is_match(contents) {
@@ -33,33 +178,44 @@ fn sanitize(contents: &str) -> String {
 }
 
 /// Fetch the contents from the given URLs and parse it as an RSS feed. Returning a vector of channels.
+/// Only HTTP URLs are fetched, use `load_channel` to read local files and `data:` URLs.
+/// Uses the default FetchConfig, use a FeedClient to configure timeouts, headers and proxies.
 pub fn get_channels(urls: &[&str]) -> Vec<Result<Channel, Box<dyn Error>>> {
-    let mut channels = Vec::new();
-    for url in urls {
-        let contents = get(url);
-        match contents {
-            Ok(contents) => {
-                let contents = sanitize(&contents);
-                let channel = parse_rss(&contents);
-                channels.push(channel);
-            }
-            Err(e) => channels.push(Err(e)),
-        }
+    match FeedClient::new(FetchConfig::default()) {
+        Ok(client) => get_channels_with(&client, urls),
+        Err(e) => urls.iter().map(|_| Err(e.to_string().into())).collect(),
     }
-    channels
+}
+
+/// Same as `get_channels`, fetching with the given Fetcher.
+pub fn get_channels_with<F: Fetcher + ?Sized>(
+    fetcher: &F,
+    urls: &[&str],
+) -> Vec<Result<Channel, Box<dyn Error>>> {
+    fetcher.get_channels(urls)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fetching::fixture::FixtureFetcher;
     use std::fs;
 
     #[test]
-    /// Test wether the function get() returns an Ok(String)
+    /// Test wether the function get() returns the body of the page and fails for unknown urls
     fn test_get() {
-        let result = get("https://www.rust-lang.org/en-US/");
-        // Check that the function succeeded
-        assert!(result.is_ok());
+        let fetcher = FixtureFetcher::new()
+            .file(
+                "https://www.rust-lang.org/en-US/",
+                "./resources/testing/example.html",
+            )
+            .unwrap();
+        let result = fetcher.get("https://www.rust-lang.org/en-US/");
+        // Check that the function returned the page
+        let f = fs::read_to_string("./resources/testing/example.html").unwrap();
+        assert_eq!(result.unwrap(), f);
+        // Check that urls without a fixture fail
+        assert!(fetcher.get("https://www.rust-lang.org/missing").is_err());
     }
 
     #[test]
@@ -73,6 +229,34 @@ mod tests {
         assert_eq!(result.unwrap().items().len(), 3);
     }
 
+    #[test]
+    /// Test wether the function read_channel() parses both RSS and sanitized feeds from a reader
+    fn test_read_channel() {
+        let f = fs::File::open("./resources/testing/example.rss").unwrap();
+        let result = read_channel(f, None);
+        assert_eq!(result.unwrap().items().len(), 3);
+
+        let feed = "<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>Feed</title><item><title>a</title></item></feed>";
+        let result = read_channel(feed.as_bytes(), None);
+        assert_eq!(result.unwrap().items().len(), 1);
+    }
+
+    #[test]
+    /// Test wether the function read_channel() transcodes feeds which aren't UTF-8
+    fn test_read_channel_encoding() {
+        let f = fs::File::open("./resources/testing/example_shift_jis.rss").unwrap();
+        let channel = read_channel(f, None).unwrap();
+        assert_eq!(channel.title(), "日本語のフィード");
+        assert_eq!(channel.items()[0].title(), Some("最初の記事"));
+
+        // A byte order mark takes precedence over the Content-Type header
+        let mut feed = b"\xEF\xBB\xBF".to_vec();
+        feed.extend_from_slice("<rss><channel><title>Ünïcode</title></channel></rss>".as_bytes());
+        let channel =
+            read_channel(feed.as_slice(), Some("text/xml; charset=windows-1252")).unwrap();
+        assert_eq!(channel.title(), "Ünïcode");
+    }
+
     #[test]
     /// Test wether the function get_channels returns an Ok(Vec<Channel>)
     fn test_get_channels() {
@@ -80,8 +264,12 @@ mod tests {
             "https://blog.rust-lang.org/feed.xml",
             "https://github.com/timeline",
         ];
-        let results = get_channels(&urls);
-        println!("{:?}", results);
+        let atom = "<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>Timeline</title></feed>";
+        let fetcher = FixtureFetcher::new()
+            .file(urls[0], "./resources/testing/example.rss")
+            .unwrap()
+            .body(urls[1], atom.as_bytes(), Some("application/atom+xml"));
+        let results = get_channels_with(&fetcher, &urls);
         // Check that the function succeeded
         for result in &results {
             assert!(result.is_ok());
@@ -89,4 +277,65 @@ mod tests {
         // Check that we got two channels
         assert_eq!(results.len(), 2);
     }
+
+    #[test]
+    /// Test wether the function read_channel_diagnosed() repairs malformed feeds and reports what it fixed
+    fn test_read_channel_recovering() {
+        let feed = "\n\n<?xml version=\"1.0\"?><rss><channel><title>Tom & Jerry&nbsp;!</title><item><title>a</title></item><item><ti";
+        let mut diagnostics = Diagnostics::new("");
+        let channel = read_channel_diagnosed(feed.as_bytes(), None, &mut diagnostics).unwrap();
+        assert_eq!(channel.title(), "Tom & Jerry\u{a0}!");
+        assert_eq!(channel.items().len(), 2);
+        assert_eq!(
+            diagnostics.warnings(),
+            [
+                "removed 2 characters before the start of the document",
+                "escaped 1 bare ampersand",
+                "replaced undeclared HTML entities &nbsp;",
+                "dropped an incomplete tag at the end of a truncated document",
+                "closed unclosed elements item, channel, rss of a truncated document"
+            ]
+        );
+
+        let feed = "<feed xmlns=\"http://www.w3.org/2005/Atom\"><title>R&D</title></feed>";
+        let mut diagnostics = Diagnostics::new("");
+        let channel = read_channel_diagnosed(feed.as_bytes(), None, &mut diagnostics).unwrap();
+        assert_eq!(channel.title(), "R&D");
+        assert_eq!(diagnostics.warnings()[1], "escaped 1 bare ampersand");
+
+        assert!(read_channel("<html><body>Not a feed</body></html>".as_bytes(), None).is_err());
+    }
+
+    #[test]
+    /// Test wether fetched channels have their relative urls resolved against xml:base and the fetch url
+    fn test_read_channel_resolves_urls() {
+        let urls = [
+            "https://example.com/feeds/a.xml",
+            "https://example.com/feeds/b.xml",
+        ];
+        let based = "<rss xml:base=\"https://cdn.example.com/\"><channel><title>A</title>\
+            <item><link>posts/1</link></item></channel></rss>";
+        let plain =
+            "<rss><channel><title>B</title><item><link>posts/1</link></item></channel></rss>";
+        let fetcher = FixtureFetcher::new()
+            .body(urls[0], based.as_bytes(), None)
+            .body(urls[1], plain.as_bytes(), None);
+        let channels: Vec<Channel> = get_channels_with(&fetcher, &urls)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            channels[0].items()[0].link(),
+            Some("https://cdn.example.com/posts/1")
+        );
+        assert_eq!(
+            channels[1].items()[0].link(),
+            Some("https://example.com/feeds/posts/1")
+        );
+        assert_eq!(
+            find_xml_base("<rss version='2.0' xml:base='/'>"),
+            Some(String::from("/"))
+        );
+        assert_eq!(find_xml_base("<rss><item xml:base=\"/\">"), None);
+    }
 }
@@ -0,0 +1,180 @@
+//! Definition and implementation of per-feed failure tracking.
+
+// std imports
+use std::collections::HashMap;
+
+// third-party imports
+use chrono::{prelude::*, Duration};
+use serde::{Deserialize, Serialize};
+
+/// Consecutive failures after which a feed is reported as unhealthy.
+pub const UNHEALTHY_FAILURES: u32 = 5;
+
+/// The longest a failing feed's poll interval is stretched to.
+pub const MAX_FAILURE_INTERVAL: Duration = Duration::days(7);
+
+/// The fetch history of a single feed.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FeedHealth {
+    consecutive_failures: u32,
+    failing_since: Option<DateTime<Utc>>,
+    last_success: Option<DateTime<Utc>>,
+    last_attempt: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// Function implementations for FeedHealth.
+impl FeedHealth {
+    /// Return the number of failed fetches since the last successful one.
+    pub fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+
+    /// Return when the current run of failures started, if the feed is failing.
+    pub fn failing_since(&self) -> Option<DateTime<Utc>> {
+        self.failing_since
+    }
+
+    /// Return when the feed was last fetched successfully.
+    pub fn last_success(&self) -> Option<DateTime<Utc>> {
+        self.last_success
+    }
+
+    /// Return when the feed was last fetched, successfully or not.
+    pub fn last_attempt(&self) -> Option<DateTime<Utc>> {
+        self.last_attempt
+    }
+
+    /// Return the message of the last error, if the feed is failing.
+    pub fn last_error(&self) -> Option<&str> {
+        self.last_error.as_deref()
+    }
+
+    /// Return whether the feed has failed fewer than `UNHEALTHY_FAILURES` times in a row.
+    pub fn is_healthy(&self) -> bool {
+        self.consecutive_failures < UNHEALTHY_FAILURES
+    }
+
+    /// Return the interval to wait before polling the feed again,
+    /// doubling the normal interval for every consecutive failure up to `MAX_FAILURE_INTERVAL`.
+    pub fn poll_interval(&self, interval: Duration) -> Duration {
+        if self.consecutive_failures == 0 {
+            return interval;
+        }
+        let factor = 2i32.saturating_pow(self.consecutive_failures.min(16));
+        (interval * factor).min(MAX_FAILURE_INTERVAL.max(interval))
+    }
+
+    /// Return when the feed should next be polled, given its normal poll interval.
+    /// Feeds which have never been fetched are due right away.
+    pub fn next_poll(&self, interval: Duration) -> Option<DateTime<Utc>> {
+        self.last_attempt
+            .map(|last_attempt| last_attempt + self.poll_interval(interval))
+    }
+}
+
+/// Tracks the health of every fetched feed, keyed by feed url.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct HealthTracker {
+    feeds: HashMap<String, FeedHealth>,
+}
+
+/// Function implementations for HealthTracker.
+impl HealthTracker {
+    /// Create a new empty HealthTracker.
+    pub fn new() -> HealthTracker {
+        HealthTracker::default()
+    }
+
+    /// Record a successful fetch of the given feed.
+    pub fn record_success(&mut self, url: &str, at: DateTime<Utc>) {
+        let health = self.feeds.entry(url.to_string()).or_default();
+        health.consecutive_failures = 0;
+        health.failing_since = None;
+        health.last_success = Some(at);
+        health.last_attempt = Some(at);
+        health.last_error = None;
+    }
+
+    /// Record a failed fetch of the given feed.
+    pub fn record_failure(&mut self, url: &str, error: &str, at: DateTime<Utc>) {
+        let health = self.feeds.entry(url.to_string()).or_default();
+        health.consecutive_failures += 1;
+        health.failing_since.get_or_insert(at);
+        health.last_attempt = Some(at);
+        health.last_error = Some(error.to_string());
+    }
+
+    /// Return the health of the given feed. Feeds which have never been fetched are healthy.
+    pub fn health(&self, url: &str) -> FeedHealth {
+        self.feeds.get(url).cloned().unwrap_or_default()
+    }
+
+    /// Return whether the given feed should be polled at the given time, given its normal poll interval.
+    pub fn is_due(&self, url: &str, interval: Duration, now: DateTime<Utc>) -> bool {
+        match self.health(url).next_poll(interval) {
+            Some(next_poll) => next_poll <= now,
+            None => true,
+        }
+    }
+
+    /// Return the urls of the feeds which are currently unhealthy.
+    pub fn unhealthy(&self) -> Vec<&str> {
+        let mut urls: Vec<&str> = self
+            .feeds
+            .iter()
+            .filter(|(_, health)| !health.is_healthy())
+            .map(|(url, _)| url.as_str())
+            .collect();
+        urls.sort_unstable();
+        urls
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_tracker() {
+        let mut tracker = HealthTracker::new();
+        let url = "https://example.com/feed";
+        let start = Utc.with_ymd_and_hms(2017, 1, 1, 12, 0, 0).unwrap();
+        let interval = Duration::hours(1);
+        assert!(tracker.is_due(url, interval, start));
+
+        tracker.record_success(url, start);
+        assert!(!tracker.is_due(url, interval, start));
+        assert!(tracker.is_due(url, interval, start + interval));
+
+        for failure in 1..=UNHEALTHY_FAILURES {
+            tracker.record_failure(url, "HTTP 503", start + Duration::hours(failure as i64));
+        }
+        let health = tracker.health(url);
+        assert_eq!(health.consecutive_failures(), UNHEALTHY_FAILURES);
+        assert_eq!(health.failing_since(), Some(start + Duration::hours(1)));
+        assert_eq!(health.last_error(), Some("HTTP 503"));
+        assert_eq!(health.poll_interval(interval), Duration::hours(32));
+        assert_eq!(tracker.unhealthy(), vec![url]);
+
+        tracker.record_success(url, start + Duration::days(1));
+        assert!(tracker.health(url).is_healthy());
+        assert!(tracker.unhealthy().is_empty());
+    }
+
+    #[test]
+    fn test_feed_health_poll_interval_cap() {
+        let mut tracker = HealthTracker::new();
+        let now = Utc.with_ymd_and_hms(2017, 1, 1, 12, 0, 0).unwrap();
+        for _ in 0..40 {
+            tracker.record_failure("a", "error", now);
+        }
+        let health = tracker.health("a");
+        assert_eq!(
+            health.poll_interval(Duration::hours(1)),
+            MAX_FAILURE_INTERVAL
+        );
+        // Intervals longer than the cap are never shortened
+        assert_eq!(health.poll_interval(Duration::days(30)), Duration::days(30));
+    }
+}
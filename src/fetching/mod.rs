@@ -1,2 +1,23 @@
 //! Fetching module.
+pub mod auth;
+pub mod body;
+pub mod client;
+pub mod compression;
+pub mod config;
+pub mod diagnostics;
+pub mod discovery;
+pub mod encoding;
+pub mod entities;
+pub mod error;
+pub mod fetched;
+pub mod fetcher;
+pub mod fixture;
 pub mod functions;
+pub mod health;
+pub mod rate_limit;
+pub mod recovery;
+pub mod redirect;
+pub mod retry;
+pub mod robots;
+pub mod scheduler;
+pub mod source;
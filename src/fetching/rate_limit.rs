@@ -0,0 +1,248 @@
+//! Definition and implementation of per-host rate limiting.
+
+// std imports
+use std::{
+    collections::HashMap,
+    sync::{Arc, Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+/// How requests to a single host are spaced out.
+/// Limits apply per host and are shared by every thread using the same FeedClient.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimit {
+    min_interval: Duration,
+    max_concurrency: usize,
+    max_wait: Duration,
+    respect_robots_txt: bool,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        RateLimit {
+            min_interval: Duration::ZERO,
+            max_concurrency: 4,
+            max_wait: Duration::from_secs(60),
+            respect_robots_txt: false,
+        }
+    }
+}
+
+/// Function implementations for RateLimit.
+impl RateLimit {
+    /// Create a new RateLimit allowing 4 concurrent requests per host without spacing them out,
+    /// and waiting at most 60 seconds for a host.
+    pub fn new() -> RateLimit {
+        RateLimit::default()
+    }
+
+    /// Set the minimum time between the start of two requests to the same host.
+    pub fn min_interval(mut self, min_interval: Duration) -> RateLimit {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// Set the maximum number of requests to the same host in flight at once, including reading their bodies.
+    /// Zero is treated as one.
+    pub fn max_concurrency(mut self, max_concurrency: usize) -> RateLimit {
+        self.max_concurrency = max_concurrency;
+        self
+    }
+
+    /// Set the longest a request waits for its host to become available.
+    /// Requests to hosts which asked to be left alone for longer fail with `FetchError::RateLimited`.
+    pub fn max_wait(mut self, max_wait: Duration) -> RateLimit {
+        self.max_wait = max_wait;
+        self
+    }
+
+    /// Set whether the robots.txt of a host is fetched and obeyed before fetching feeds from it.
+    pub fn respect_robots_txt(mut self, respect_robots_txt: bool) -> RateLimit {
+        self.respect_robots_txt = respect_robots_txt;
+        self
+    }
+
+    /// Return whether the robots.txt of a host is obeyed.
+    pub fn respects_robots_txt(&self) -> bool {
+        self.respect_robots_txt
+    }
+}
+
+/// The requests in flight to a single host, and when the next one may start.
+#[derive(Debug, Default)]
+struct HostState {
+    active: usize,
+    next_request: Option<Instant>,
+    interval: Option<Duration>,
+}
+
+/// Enforces a RateLimit across every host, blocking requests until their host is available.
+#[derive(Debug)]
+pub(crate) struct HostLimiter {
+    limit: RateLimit,
+    hosts: Mutex<HashMap<String, HostState>>,
+    available: Condvar,
+}
+
+/// Function implementations for HostLimiter.
+impl HostLimiter {
+    /// Create a new HostLimiter enforcing the given RateLimit.
+    pub(crate) fn new(limit: RateLimit) -> Arc<HostLimiter> {
+        Arc::new(HostLimiter {
+            limit,
+            hosts: Mutex::new(HashMap::new()),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Wait until a request to the given host may start, returning a permit held for as long as the request is in flight.
+    /// Fails with the remaining wait if the host won't be available within the configured maximum wait,
+    /// or with the maximum wait if no other request to the host finished within it.
+    pub(crate) fn acquire(self: &Arc<Self>, host: &str) -> Result<Permit, Duration> {
+        let max_concurrency = self.limit.max_concurrency.max(1);
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let start = Instant::now();
+        loop {
+            let now = Instant::now();
+            let state = hosts.entry(host.to_string()).or_default();
+            let wait = state
+                .next_request
+                .map(|next| next.saturating_duration_since(now))
+                .unwrap_or_default();
+            let remaining = self.limit.max_wait.saturating_sub(now - start);
+            if wait > remaining {
+                return Err(wait);
+            }
+            if wait.is_zero() && state.active < max_concurrency {
+                state.active += 1;
+                let interval = state
+                    .interval
+                    .unwrap_or_default()
+                    .max(self.limit.min_interval);
+                state.next_request = Some(now + interval);
+                return Ok(Permit {
+                    limiter: Arc::clone(self),
+                    host: host.to_string(),
+                });
+            }
+            if wait.is_zero() && remaining.is_zero() {
+                return Err(self.limit.max_wait);
+            }
+            // Woken up early when a permit is released or the host backs off
+            let timeout = if wait.is_zero() { remaining } else { wait };
+            hosts = self
+                .available
+                .wait_timeout(hosts, timeout)
+                .unwrap_or_else(|e| e.into_inner())
+                .0;
+        }
+    }
+
+    /// Hold off requests to the given host for the given duration, e.g. because it responded with a `Retry-After`.
+    pub(crate) fn back_off(&self, host: &str, wait: Duration) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        let state = hosts.entry(host.to_string()).or_default();
+        let until = Instant::now() + wait;
+        if state.next_request.is_none_or(|next| next < until) {
+            state.next_request = Some(until);
+        }
+        self.available.notify_all();
+    }
+
+    /// Space out requests to the given host by at least the given interval, e.g. because its robots.txt asked to.
+    pub(crate) fn set_interval(&self, host: &str, interval: Duration) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        hosts.entry(host.to_string()).or_default().interval = Some(interval);
+    }
+
+    /// Return whether the robots.txt of a host is obeyed.
+    pub(crate) fn respects_robots_txt(&self) -> bool {
+        self.limit.respect_robots_txt
+    }
+
+    /// Mark a request to the given host as finished.
+    fn release(&self, host: &str) {
+        let mut hosts = self.hosts.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(state) = hosts.get_mut(host) {
+            state.active = state.active.saturating_sub(1);
+        }
+        self.available.notify_all();
+    }
+}
+
+/// Permission to have a request to a host in flight, released when dropped.
+#[derive(Debug)]
+pub(crate) struct Permit {
+    limiter: Arc<HostLimiter>,
+    host: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.limiter.release(&self.host);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn test_host_limiter_interval() {
+        let limit = RateLimit::new().min_interval(Duration::from_millis(100));
+        let limiter = HostLimiter::new(limit);
+        let start = Instant::now();
+        drop(limiter.acquire("a.com").unwrap());
+        drop(limiter.acquire("b.com").unwrap());
+        assert!(start.elapsed() < Duration::from_millis(100));
+        drop(limiter.acquire("a.com").unwrap());
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_host_limiter_concurrency() {
+        let limiter = HostLimiter::new(RateLimit::new().max_concurrency(1));
+        let permit = limiter.acquire("a.com").unwrap();
+        let start = Instant::now();
+        let waiting = {
+            let limiter = Arc::clone(&limiter);
+            thread::spawn(move || {
+                drop(limiter.acquire("a.com").unwrap());
+                start.elapsed()
+            })
+        };
+        thread::sleep(Duration::from_millis(100));
+        drop(permit);
+        assert!(waiting.join().unwrap() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_host_limiter_concurrency_max_wait() {
+        let limit = RateLimit::new()
+            .max_concurrency(1)
+            .max_wait(Duration::from_millis(50));
+        let limiter = HostLimiter::new(limit);
+        let _permit = limiter.acquire("a.com").unwrap();
+        let start = Instant::now();
+        assert_eq!(
+            limiter.acquire("a.com").unwrap_err(),
+            Duration::from_millis(50)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        assert!(limiter.acquire("b.com").is_ok());
+    }
+
+    #[test]
+    fn test_host_limiter_back_off() {
+        let limiter = HostLimiter::new(RateLimit::new().max_wait(Duration::from_secs(1)));
+        limiter.back_off("a.com", Duration::from_secs(120));
+        assert!(limiter.acquire("a.com").unwrap_err() > Duration::from_secs(60));
+        assert!(limiter.acquire("b.com").is_ok());
+
+        limiter.back_off("b.com", Duration::from_millis(50));
+        let start = Instant::now();
+        assert!(limiter.acquire("b.com").is_ok());
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
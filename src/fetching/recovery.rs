@@ -0,0 +1,314 @@
+//! Set of functions for recovering malformed feeds the XML parser rejects.
+
+// Local Imports
+use super::entities::html_entity;
+
+/// The entities XML defines without a DTD.
+const XML_ENTITIES: [&str; 5] = ["amp", "lt", "gt", "quot", "apos"];
+
+/// The fixes applied by `recover`, counted by kind.
+#[derive(Debug, Default)]
+struct Fixes {
+    leading: usize,
+    control_chars: usize,
+    ampersands: usize,
+    html_entities: Vec<String>,
+    incomplete_tag: bool,
+    unterminated: Vec<&'static str>,
+    closed: Vec<String>,
+}
+
+/// Function implementations for Fixes.
+impl Fixes {
+    /// Describe the applied fixes, one warning per kind.
+    fn warnings(self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.leading > 0 {
+            warnings.push(format!(
+                "removed {} before the start of the document",
+                plural(self.leading, "character")
+            ));
+        }
+        if self.control_chars > 0 {
+            warnings.push(format!(
+                "removed {}",
+                plural(self.control_chars, "control character")
+            ));
+        }
+        if self.ampersands > 0 {
+            warnings.push(format!(
+                "escaped {}",
+                plural(self.ampersands, "bare ampersand")
+            ));
+        }
+        if !self.html_entities.is_empty() {
+            warnings.push(format!(
+                "replaced undeclared HTML entities {}",
+                self.html_entities.join(", ")
+            ));
+        }
+        if self.incomplete_tag {
+            warnings.push(String::from(
+                "dropped an incomplete tag at the end of a truncated document",
+            ));
+        }
+        for unterminated in self.unterminated {
+            warnings.push(format!(
+                "terminated a {} cut off by a truncated document",
+                unterminated
+            ));
+        }
+        if !self.closed.is_empty() {
+            warnings.push(format!(
+                "closed unclosed elements {} of a truncated document",
+                self.closed.join(", ")
+            ));
+        }
+        warnings
+    }
+}
+
+/// Return the given count followed by the given noun, pluralized if needed.
+fn plural(count: usize, noun: &str) -> String {
+    match count {
+        1 => format!("1 {}", noun),
+        _ => format!("{} {}s", count, noun),
+    }
+}
+
+/// Repair the common ways feeds are malformed, returning the repaired contents along with a warning per kind of fix.
+/// Junk before the first tag and control characters XML doesn't allow are removed, bare ampersands are escaped,
+/// HTML entities are replaced by character references, and the elements still open at the end of a truncated document are closed.
+/// CDATA sections and comments are left as they are.
+pub(crate) fn recover(contents: &str) -> (String, Vec<String>) {
+    let mut fixes = Fixes::default();
+    let start = contents.find('<').unwrap_or(0);
+    fixes.leading = contents[..start].chars().count();
+    let contents: String = contents[start..]
+        .chars()
+        .filter(|c| {
+            let allowed = is_xml_char(*c);
+            if !allowed {
+                fixes.control_chars += 1;
+            }
+            allowed
+        })
+        .collect();
+
+    let mut recovered = String::with_capacity(contents.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = contents.as_str();
+    while !rest.is_empty() {
+        let verbatim = [
+            ("<![CDATA[", "]]>", "CDATA section"),
+            ("<!--", "-->", "comment"),
+            ("<?", "?>", "processing instruction"),
+        ]
+        .into_iter()
+        .find(|(start, _, _)| rest.starts_with(start));
+        if let Some((start, end, name)) = verbatim {
+            match rest[start.len()..].find(end) {
+                Some(position) => {
+                    let len = start.len() + position + end.len();
+                    recovered.push_str(&rest[..len]);
+                    rest = &rest[len..];
+                }
+                None => {
+                    recovered.push_str(rest);
+                    recovered.push_str(end);
+                    fixes.unterminated.push(name);
+                    rest = "";
+                }
+            }
+        } else if rest.starts_with('<') {
+            let len = match tag_len(rest) {
+                Some(len) => len,
+                None => {
+                    fixes.incomplete_tag = true;
+                    break;
+                }
+            };
+            let tag = &rest[..len];
+            track_element(tag, &mut open);
+            recovered.push_str(&fix_entities(tag, &mut fixes));
+            rest = &rest[len..];
+        } else {
+            let len = rest.find('<').unwrap_or(rest.len());
+            recovered.push_str(&fix_entities(&rest[..len], &mut fixes));
+            rest = &rest[len..];
+        }
+    }
+    while let Some(name) = open.pop() {
+        recovered.push_str(&format!("</{}>", name));
+        fixes.closed.push(name);
+    }
+    (recovered, fixes.warnings())
+}
+
+/// Return whether XML allows the given character, which excludes most control characters.
+fn is_xml_char(c: char) -> bool {
+    !matches!(
+        c,
+        '\u{0}'..='\u{8}' | '\u{b}' | '\u{c}' | '\u{e}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}'
+    )
+}
+
+/// Return the length of the tag at the start of the given string, up to and including its `>`,
+/// skipping `>` inside quoted attribute values. Returns `None` if the tag is never closed.
+fn tag_len(rest: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(open), _) if c == open => quote = None,
+            (None, '>') => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Update the stack of open elements with the given tag.
+/// A closing tag closes every element opened after the element it closes, closing tags of elements which aren't open are ignored.
+fn track_element(tag: &str, open: &mut Vec<String>) {
+    if tag.starts_with("<!") || tag.ends_with("/>") {
+        return;
+    }
+    let (closing, name) = match tag.strip_prefix("</") {
+        Some(name) => (true, name),
+        None => (false, &tag[1..]),
+    };
+    let name: String = name
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '>' && *c != '/')
+        .collect();
+    if name.is_empty() {
+        return;
+    }
+    if !closing {
+        open.push(name);
+    } else if let Some(position) = open.iter().rposition(|open| *open == name) {
+        open.truncate(position);
+    }
+}
+
+/// Escape the ampersands in the given text which don't start an entity XML knows,
+/// replacing HTML entities by character references.
+fn fix_entities(text: &str, fixes: &mut Fixes) -> String {
+    let mut fixed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(position) = rest.find('&') {
+        fixed.push_str(&rest[..position]);
+        rest = &rest[position + 1..];
+        let reference = rest
+            .find(';')
+            .map(|end| &rest[..end])
+            .filter(|reference| !reference.is_empty() && reference.len() <= 32);
+        match reference {
+            Some(reference) if is_xml_reference(reference) => fixed.push('&'),
+            Some(reference) => match html_entity(reference) {
+                Some(characters) => {
+                    for character in characters.chars() {
+                        fixed.push_str(&format!("&#{};", u32::from(character)));
+                    }
+                    rest = &rest[reference.len() + 1..];
+                    let name = format!("&{};", reference);
+                    if !fixes.html_entities.contains(&name) {
+                        fixes.html_entities.push(name);
+                    }
+                }
+                None => {
+                    fixed.push_str("&amp;");
+                    fixes.ampersands += 1;
+                }
+            },
+            None => {
+                fixed.push_str("&amp;");
+                fixes.ampersands += 1;
+            }
+        }
+    }
+    fixed.push_str(rest);
+    fixed
+}
+
+/// Return whether the given reference, the text between `&` and `;`, is one XML knows.
+fn is_xml_reference(reference: &str) -> bool {
+    if XML_ENTITIES.contains(&reference) {
+        return true;
+    }
+    let number = match reference.strip_prefix('#') {
+        Some(number) => number,
+        None => return false,
+    };
+    let code_point = match number.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => number.parse::<u32>(),
+    };
+    code_point.ok().and_then(char::from_u32).is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetching::functions::parse_rss;
+
+    #[test]
+    fn test_recover_entities() {
+        let feed = "\u{feff}junk\n<rss><channel><title>Tom &amp; Jerry & Co &nbsp;&hellip; &#8212; &bogus;</title>\
+            <link>https://example.com/?a=1&b=2</link><description><![CDATA[a & b]]></description></channel></rss>";
+        assert!(parse_rss(feed).is_err());
+        let (recovered, warnings) = recover(feed);
+        let channel = parse_rss(&recovered).unwrap();
+        assert_eq!(channel.title(), "Tom & Jerry & Co \u{a0}… — &bogus;");
+        assert_eq!(channel.link(), "https://example.com/?a=1&b=2");
+        assert_eq!(channel.description(), "a & b");
+        assert_eq!(
+            warnings,
+            [
+                "removed 6 characters before the start of the document",
+                "escaped 3 bare ampersands",
+                "replaced undeclared HTML entities &nbsp;, &hellip;"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recover_control_characters() {
+        let feed = "<rss><channel><title>a\u{0}b\u{1b}c\td</title></channel></rss>";
+        let (recovered, warnings) = recover(feed);
+        assert_eq!(parse_rss(&recovered).unwrap().title(), "abc\td");
+        assert_eq!(warnings, ["removed 2 control characters"]);
+    }
+
+    #[test]
+    fn test_recover_truncated() {
+        let feed =
+            "<rss><channel><title>Truncated</title><item><title>a</title></item><item><title>b</ti";
+        let (recovered, warnings) = recover(feed);
+        let channel = parse_rss(&recovered).unwrap();
+        assert_eq!(channel.title(), "Truncated");
+        assert_eq!(channel.items().len(), 2);
+        assert_eq!(
+            warnings,
+            [
+                "dropped an incomplete tag at the end of a truncated document",
+                "closed unclosed elements title, item, channel, rss of a truncated document"
+            ]
+        );
+
+        let (recovered, _) = recover("<rss><channel><description><![CDATA[cut");
+        assert_eq!(
+            recovered,
+            "<rss><channel><description><![CDATA[cut]]></description></channel></rss>"
+        );
+    }
+
+    #[test]
+    fn test_recover_valid() {
+        let feed = std::fs::read_to_string("./resources/testing/example.rss").unwrap();
+        let (recovered, warnings) = recover(&feed);
+        assert!(warnings.is_empty());
+        assert_eq!(recovered, feed);
+    }
+}
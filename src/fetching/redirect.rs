@@ -0,0 +1,154 @@
+//! Definition and implementation of redirect tracking and the subscription updates they lead to.
+
+// std imports
+use std::error::Error;
+
+// Local Imports
+use super::error::FetchError;
+
+/// A single redirect followed while fetching a feed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    status: u16,
+    from: String,
+    to: String,
+}
+
+/// Function implementations for Redirect.
+impl Redirect {
+    /// Create a new Redirect from the given url to the given location.
+    pub fn new(status: u16, from: &str, to: &str) -> Redirect {
+        Redirect {
+            status,
+            from: from.to_string(),
+            to: to.to_string(),
+        }
+    }
+
+    /// Return the status code of the redirect.
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    /// Return the url which was redirected.
+    pub fn from(&self) -> &str {
+        &self.from
+    }
+
+    /// Return the url redirected to.
+    pub fn to(&self) -> &str {
+        &self.to
+    }
+
+    /// Return whether the redirect is permanent, i.e. a 301 or 308.
+    pub fn is_permanent(&self) -> bool {
+        matches!(self.status, 301 | 308)
+    }
+}
+
+/// A change to a subscription suggested by the outcome of a fetch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionUpdate {
+    /// The feed has permanently moved to a new url.
+    Moved { from: String, to: String },
+    /// The feed has been removed for good, the server responded with 410 Gone.
+    Gone { url: String },
+}
+
+/// Function implementations for SubscriptionUpdate.
+impl SubscriptionUpdate {
+    /// Return the update suggested by the given redirects, followed while fetching the given url.
+    /// Only the unbroken run of permanent redirects at the start of the chain is taken into account,
+    /// since a temporary redirect means the url before it should be kept.
+    pub fn from_redirects(url: &str, redirects: &[Redirect]) -> Option<SubscriptionUpdate> {
+        let to = redirects
+            .iter()
+            .take_while(|redirect| redirect.is_permanent())
+            .last()?
+            .to();
+        if to == url {
+            return None;
+        }
+        Some(SubscriptionUpdate::Moved {
+            from: url.to_string(),
+            to: to.to_string(),
+        })
+    }
+
+    /// Return the update suggested by the given fetch error, if the feed is gone.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Option<SubscriptionUpdate> {
+        match error.downcast_ref::<FetchError>() {
+            Some(error) if error.status() == Some(410) => Some(SubscriptionUpdate::Gone {
+                url: error.requested_url().to_string(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Return the feed url the update applies to.
+    pub fn url(&self) -> &str {
+        match self {
+            SubscriptionUpdate::Moved { from, .. } => from,
+            SubscriptionUpdate::Gone { url } => url,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_update_from_redirects() {
+        let url = "http://example.com/feed";
+        let redirects = vec![
+            Redirect::new(301, url, "https://example.com/feed"),
+            Redirect::new(308, "https://example.com/feed", "https://example.com/rss"),
+            Redirect::new(
+                302,
+                "https://example.com/rss",
+                "https://cdn.example.com/rss",
+            ),
+            Redirect::new(
+                301,
+                "https://cdn.example.com/rss",
+                "https://cdn.example.com/x",
+            ),
+        ];
+        assert_eq!(
+            SubscriptionUpdate::from_redirects(url, &redirects),
+            Some(SubscriptionUpdate::Moved {
+                from: url.to_string(),
+                to: "https://example.com/rss".to_string(),
+            })
+        );
+        assert_eq!(
+            SubscriptionUpdate::from_redirects(url, &redirects[2..]),
+            None
+        );
+        assert_eq!(SubscriptionUpdate::from_redirects(url, &[]), None);
+    }
+
+    #[test]
+    fn test_subscription_update_from_error() {
+        let gone: Box<dyn Error> = Box::new(FetchError::Status {
+            url: "https://example.com/rss".to_string(),
+            requested_url: "https://example.com/feed".to_string(),
+            status: 410,
+            retry_after: None,
+        });
+        assert_eq!(
+            SubscriptionUpdate::from_error(gone.as_ref()),
+            Some(SubscriptionUpdate::Gone {
+                url: "https://example.com/feed".to_string()
+            })
+        );
+        let missing: Box<dyn Error> = Box::new(FetchError::Status {
+            url: "https://example.com/feed".to_string(),
+            requested_url: "https://example.com/feed".to_string(),
+            status: 404,
+            retry_after: None,
+        });
+        assert_eq!(SubscriptionUpdate::from_error(missing.as_ref()), None);
+    }
+}
@@ -0,0 +1,182 @@
+//! Definition and implementation of the retry policy.
+
+// std imports
+use std::{
+    cell::Cell,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// third-party imports
+use chrono::prelude::*;
+
+/// How failed requests are retried.
+/// The delay before retry `n` (starting at zero) is `base_delay * 2^n`, capped at `max_delay`,
+/// of which the upper half is randomised to spread out retries from many clients.
+/// A `Retry-After` sent by the server replaces the computed delay, unless it is longer than `max_delay`,
+/// in which case the request isn't retried at all.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    max_retries: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+            jitter: true,
+        }
+    }
+}
+
+/// Function implementations for RetryPolicy.
+impl RetryPolicy {
+    /// Create a new RetryPolicy retrying twice, starting at half a second and waiting at most 30 seconds.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy::default()
+    }
+
+    /// Create a RetryPolicy which never retries.
+    pub fn none() -> RetryPolicy {
+        RetryPolicy::default().max_retries(0)
+    }
+
+    /// Set the maximum number of retries after the first attempt.
+    pub fn max_retries(mut self, max_retries: u32) -> RetryPolicy {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry.
+    pub fn base_delay(mut self, base_delay: Duration) -> RetryPolicy {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Set the maximum delay between two attempts.
+    pub fn max_delay(mut self, max_delay: Duration) -> RetryPolicy {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Set whether the delays are randomised.
+    pub fn jitter(mut self, jitter: bool) -> RetryPolicy {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Return the maximum number of retries after the first attempt.
+    pub fn retries(&self) -> u32 {
+        self.max_retries
+    }
+
+    /// Return the delay before the given retry, starting at zero.
+    /// Returns `None` if the server asked to wait longer than `max_delay`, meaning the request shouldn't be retried.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if let Some(retry_after) = retry_after {
+            return Some(retry_after).filter(|retry_after| *retry_after <= self.max_delay);
+        }
+        let exponential = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_delay);
+        if self.jitter {
+            let half = exponential / 2;
+            Some(half + half.mul_f64(random_fraction()))
+        } else {
+            Some(exponential)
+        }
+    }
+}
+
+/// Parse the value of a `Retry-After` header, which is either a number of seconds or an HTTP date.
+pub fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.with_timezone(&Utc) - now;
+    Some(wait.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Return a pseudo-random number in `[0, 1)`, good enough for spreading out retries.
+fn random_fraction() -> f64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(0)
+                | 1,
+        );
+    }
+    STATE.with(|state| {
+        // xorshift64*
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        (x.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy_delay() {
+        let policy = RetryPolicy::new()
+            .base_delay(Duration::from_secs(1))
+            .max_delay(Duration::from_secs(5))
+            .jitter(false);
+        assert_eq!(policy.delay(0, None), Some(Duration::from_secs(1)));
+        assert_eq!(policy.delay(1, None), Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(2, None), Some(Duration::from_secs(4)));
+        assert_eq!(policy.delay(3, None), Some(Duration::from_secs(5)));
+        assert_eq!(policy.delay(40, None), Some(Duration::from_secs(5)));
+
+        // Retry-After replaces the computed delay, and isn't retried at all when it is too long
+        let retry_after = Some(Duration::from_secs(3));
+        assert_eq!(policy.delay(0, retry_after), Some(Duration::from_secs(3)));
+        let retry_after = Some(Duration::from_secs(5));
+        assert_eq!(policy.delay(0, retry_after), Some(Duration::from_secs(5)));
+        let retry_after = Some(Duration::from_secs(60));
+        assert_eq!(policy.delay(0, retry_after), None);
+    }
+
+    #[test]
+    fn test_retry_policy_jitter() {
+        let policy = RetryPolicy::new().base_delay(Duration::from_secs(4));
+        for _ in 0..100 {
+            let delay = policy.delay(0, None).unwrap();
+            assert!(delay >= Duration::from_secs(2));
+            assert!(delay <= Duration::from_secs(4));
+        }
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        let now = Utc.with_ymd_and_hms(2015, 10, 21, 7, 28, 0).unwrap();
+        assert_eq!(
+            parse_retry_after("120", now),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:30:00 GMT", now),
+            Some(Duration::from_secs(120))
+        );
+        // Dates in the past mean retrying right away
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Definition and implementation of robots.txt parsing.
+
+// std imports
+use std::time::Duration;
+
+/// The rules of a robots.txt which apply to a single user agent.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub(crate) struct Robots {
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+/// Function implementations for Robots.
+impl Robots {
+    /// Parse the given robots.txt, keeping the rules for the given user agent.
+    /// The groups naming the product token of the user agent are used if there are any, otherwise the `*` group.
+    pub(crate) fn parse(contents: &str, user_agent: &str) -> Robots {
+        let token = user_agent
+            .split(|c: char| c == '/' || c.is_whitespace())
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+        let mut specific = Robots::default();
+        let mut wildcard = Robots::default();
+        // The agents of the current group, and whether its rules have started
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        for line in contents.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+            if key == "user-agent" {
+                if in_rules {
+                    agents.clear();
+                    in_rules = false;
+                }
+                agents.push(value.to_lowercase());
+                continue;
+            }
+            in_rules = true;
+            let targets = [
+                (agents.contains(&token), &mut specific),
+                (agents.iter().any(|agent| agent == "*"), &mut wildcard),
+            ];
+            for (applies, robots) in targets {
+                if !applies {
+                    continue;
+                }
+                match key.as_str() {
+                    "allow" | "disallow" if !value.is_empty() => {
+                        robots.rules.push((key == "allow", value.to_string()))
+                    }
+                    "crawl-delay" => {
+                        robots.crawl_delay = value
+                            .parse::<f64>()
+                            .ok()
+                            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if specific.rules.is_empty() && specific.crawl_delay.is_none() {
+            wildcard
+        } else {
+            specific
+        }
+    }
+
+    /// Return whether the given path, including its query, may be fetched.
+    /// The longest matching rule wins, with allow rules winning ties.
+    pub(crate) fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    /// Return the delay between requests asked for, if any.
+    pub(crate) fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Return whether the given robots.txt pattern matches the start of the given path.
+/// `*` matches any run of characters and a trailing `$` anchors the pattern to the end of the path.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match path.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_robots() {
+        let contents = "
+            # Everyone
+            User-agent: *
+            Disallow: /private
+            Allow: /private/feed.xml
+
+            User-agent: FeRSS
+            User-agent: OtherBot
+            Disallow: /*.rss$
+            Crawl-delay: 2
+        ";
+        let robots = Robots::parse(contents, "FeRSS/0.1.0 (+https://example.com)");
+        assert!(!robots.is_allowed("/blog/index.rss"));
+        assert!(robots.is_allowed("/blog/index.rss?page=2"));
+        assert!(robots.is_allowed("/private"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_secs(2)));
+
+        let robots = Robots::parse(contents, "SomeBot/1.0");
+        assert!(!robots.is_allowed("/private/other"));
+        assert!(robots.is_allowed("/private/feed.xml"));
+        assert!(robots.is_allowed("/blog/index.rss"));
+        assert_eq!(robots.crawl_delay(), None);
+
+        assert!(Robots::parse("", "FeRSS").is_allowed("/"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Definition and implementation of the refresh scheduler.
+
+// std imports
+use std::collections::HashMap;
+
+// third-party imports
+use chrono::{prelude::*, Duration, DurationRound};
+use rss::{extension::syndication::UpdatePeriod, Channel};
+
+// Local Imports
+use super::health::HealthTracker;
+
+/// The poll interval of feeds which give no hints and have no observed update frequency.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::hours(1);
+
+/// The refresh hints published by a channel, in its `<ttl>`, `<skipHours>`, `<skipDays>` and `sy:updatePeriod` elements.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RefreshHints {
+    ttl: Option<Duration>,
+    update_period: Option<Duration>,
+    skip_hours: Vec<u32>,
+    skip_days: Vec<Weekday>,
+}
+
+/// Function implementations for RefreshHints.
+impl RefreshHints {
+    /// Read the refresh hints of the given channel, ignoring values which can't be parsed.
+    /// A `<ttl>` too long to represent is clamped to the longest Duration.
+    pub fn from_channel(channel: &Channel) -> RefreshHints {
+        let ttl = channel
+            .ttl()
+            .and_then(|ttl| ttl.trim().parse::<i64>().ok())
+            .filter(|minutes| *minutes > 0)
+            .map(|minutes| Duration::try_minutes(minutes).unwrap_or(Duration::MAX));
+        let update_period = channel.syndication_ext().map(|syndication| {
+            let period = match syndication.period() {
+                UpdatePeriod::Hourly => Duration::hours(1),
+                UpdatePeriod::Daily => Duration::days(1),
+                UpdatePeriod::Weekly => Duration::weeks(1),
+                UpdatePeriod::Monthly => Duration::days(30),
+                UpdatePeriod::Yearly => Duration::days(365),
+            };
+            period / i32::try_from(syndication.frequency().max(1)).unwrap_or(i32::MAX)
+        });
+        let skip_hours = channel
+            .skip_hours()
+            .iter()
+            .filter_map(|hour| hour.trim().parse::<u32>().ok())
+            .filter(|hour| *hour < 24)
+            .collect();
+        let skip_days = channel
+            .skip_days()
+            .iter()
+            .filter_map(|day| day.trim().parse::<Weekday>().ok())
+            .collect();
+        RefreshHints {
+            ttl,
+            update_period,
+            skip_hours,
+            skip_days,
+        }
+    }
+
+    /// Return how long the channel may be cached, from its `<ttl>`.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// Return how often the channel says it is updated, from its `sy:updatePeriod` and `sy:updateFrequency`.
+    pub fn update_period(&self) -> Option<Duration> {
+        self.update_period
+    }
+
+    /// Return the hours, in GMT, during which the channel shouldn't be polled.
+    pub fn skip_hours(&self) -> &[u32] {
+        &self.skip_hours
+    }
+
+    /// Return the days, in GMT, on which the channel shouldn't be polled.
+    pub fn skip_days(&self) -> &[Weekday] {
+        &self.skip_days
+    }
+
+    /// Return the shortest interval the channel asks to be polled at, the longest of its ttl and update period.
+    pub fn interval(&self) -> Option<Duration> {
+        self.ttl.max(self.update_period)
+    }
+
+    /// Return whether the channel asks not to be polled at the given time.
+    pub fn is_skipped(&self, at: DateTime<Utc>) -> bool {
+        self.skip_hours.contains(&at.hour()) || self.skip_days.contains(&at.weekday())
+    }
+
+    /// Return the first time at or after the given time at which the channel may be polled.
+    /// Channels which skip every hour of the week are polled as if they skipped nothing.
+    pub fn next_allowed(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let mut next = at;
+        for _ in 0..24 * 7 {
+            if !self.is_skipped(next) {
+                return next;
+            }
+            next = match next.duration_trunc(Duration::hours(1)) {
+                Ok(hour) => hour + Duration::hours(1),
+                Err(_) => return at,
+            };
+        }
+        at
+    }
+}
+
+/// Computes when every feed is next due, from its refresh hints, observed update frequency and failure backoff.
+#[derive(Debug, Clone)]
+pub struct Scheduler {
+    default_interval: Duration,
+    min_interval: Duration,
+    max_interval: Duration,
+    feeds: HashMap<String, ScheduledFeed>,
+}
+
+/// What the Scheduler knows about a single feed.
+#[derive(Debug, Default, Clone)]
+struct ScheduledFeed {
+    hints: RefreshHints,
+    observed_interval: Option<Duration>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Scheduler {
+            default_interval: DEFAULT_POLL_INTERVAL,
+            min_interval: Duration::minutes(15),
+            max_interval: Duration::days(1),
+            feeds: HashMap::new(),
+        }
+    }
+}
+
+/// Function implementations for Scheduler.
+impl Scheduler {
+    /// Create a new empty Scheduler, polling feeds hourly by default and between every 15 minutes and once a day.
+    pub fn new() -> Scheduler {
+        Scheduler::default()
+    }
+
+    /// Set the poll interval of feeds which give no hints and have no observed update frequency.
+    pub fn default_interval(mut self, interval: Duration) -> Scheduler {
+        self.default_interval = interval;
+        self
+    }
+
+    /// Set the shortest poll interval, regardless of what feeds ask for.
+    pub fn min_interval(mut self, interval: Duration) -> Scheduler {
+        self.min_interval = interval;
+        self
+    }
+
+    /// Set the longest poll interval before failure backoff, regardless of what feeds ask for.
+    /// A `<ttl>` or update period longer than this is cut short, so every feed is polled at least this often.
+    pub fn max_interval(mut self, interval: Duration) -> Scheduler {
+        self.max_interval = interval;
+        self
+    }
+
+    /// Add the given feed to the schedule, or refresh its hints from a newly fetched channel.
+    pub fn update(&mut self, url: &str, channel: &Channel) {
+        self.feeds.entry(url.to_string()).or_default().hints = RefreshHints::from_channel(channel);
+    }
+
+    /// Add the given feed to the schedule without any hints, e.g. before it has been fetched for the first time.
+    pub fn add(&mut self, url: &str) {
+        self.feeds.entry(url.to_string()).or_default();
+    }
+
+    /// Remove the given feed from the schedule.
+    pub fn remove(&mut self, url: &str) {
+        self.feeds.remove(url);
+    }
+
+    /// Set the interval at which the given feed has been observed to publish new items.
+    pub fn set_observed_interval(&mut self, url: &str, interval: Option<Duration>) {
+        self.feeds
+            .entry(url.to_string())
+            .or_default()
+            .observed_interval = interval;
+    }
+
+    /// Return the refresh hints of the given feed.
+    pub fn hints(&self, url: &str) -> RefreshHints {
+        self.feeds
+            .get(url)
+            .map(|feed| feed.hints.clone())
+            .unwrap_or_default()
+    }
+
+    /// Return the poll interval of the given feed, before failure backoff.
+    /// This is the observed update frequency, or the default interval if there is none,
+    /// stretched to the interval the feed asks for and bounded by the minimum and maximum interval.
+    /// The maximum interval wins over the feed's `<ttl>` and update period.
+    pub fn interval(&self, url: &str) -> Duration {
+        match self.feeds.get(url) {
+            Some(feed) => self.interval_with(feed.observed_interval, &feed.hints),
+            None => self.interval_with(None, &RefreshHints::default()),
+        }
+    }
+
+    /// Return the poll interval of a feed with the given observed update frequency and refresh hints, the way `interval` does.
+    pub fn interval_with(
+        &self,
+        observed_interval: Option<Duration>,
+        hints: &RefreshHints,
+    ) -> Duration {
+        let interval = observed_interval.unwrap_or(self.default_interval);
+        interval
+            .max(hints.interval().unwrap_or(interval))
+            .min(self.max_interval)
+            .max(self.min_interval)
+    }
+
+    /// Return when the given feed is next due, given the fetch history in the HealthTracker.
+    /// Feeds which have never been fetched are due right away.
+    pub fn next_due(&self, url: &str, tracker: &HealthTracker) -> Option<DateTime<Utc>> {
+        let next_poll = tracker.health(url).next_poll(self.interval(url))?;
+        Some(self.hints(url).next_allowed(next_poll))
+    }
+
+    /// Return the urls of the feeds due at the given time, the longest overdue first.
+    pub fn due(&self, tracker: &HealthTracker, now: DateTime<Utc>) -> Vec<&str> {
+        let mut due: Vec<(Option<DateTime<Utc>>, &str)> = self
+            .feeds
+            .iter()
+            .filter(|(_, feed)| !feed.hints.is_skipped(now))
+            .map(|(url, _)| (self.next_due(url, tracker), url.as_str()))
+            .filter(|(next_due, _)| next_due.is_none_or(|next_due| next_due <= now))
+            .collect();
+        due.sort_unstable();
+        due.into_iter().map(|(_, url)| url).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rss::extension::syndication::SyndicationExtension;
+
+    #[test]
+    fn test_refresh_hints() {
+        let mut channel = Channel::default();
+        channel.set_ttl("120".to_string());
+        channel.set_skip_hours(vec!["0".to_string(), "1".to_string(), "x".to_string()]);
+        channel.set_skip_days(vec!["Sunday".to_string()]);
+        let mut syndication = SyndicationExtension::default();
+        syndication.set_period(UpdatePeriod::Daily);
+        syndication.set_frequency(4);
+        channel.set_syndication_ext(syndication);
+
+        let hints = RefreshHints::from_channel(&channel);
+        assert_eq!(hints.ttl(), Some(Duration::hours(2)));
+        assert_eq!(hints.update_period(), Some(Duration::hours(6)));
+        assert_eq!(hints.interval(), Some(Duration::hours(6)));
+        assert_eq!(hints.skip_hours(), [0, 1]);
+        assert_eq!(hints.skip_days(), [Weekday::Sun]);
+
+        // Frequencies beyond the range of i32 don't wrap around
+        let mut syndication = SyndicationExtension::default();
+        syndication.set_period(UpdatePeriod::Hourly);
+        syndication.set_frequency(u32::MAX);
+        channel.set_syndication_ext(syndication);
+        let update_period = RefreshHints::from_channel(&channel)
+            .update_period()
+            .unwrap();
+        assert!(update_period >= Duration::zero());
+
+        // A ttl too long to represent is clamped instead of overflowing
+        let mut huge = Channel::default();
+        huge.set_ttl(i64::MAX.to_string());
+        let huge_hints = RefreshHints::from_channel(&huge);
+        assert_eq!(huge_hints.ttl(), Some(Duration::MAX));
+        let scheduler = Scheduler::new();
+        assert_eq!(
+            scheduler.interval_with(None, &huge_hints),
+            Duration::days(1)
+        );
+
+        // Saturday 23:30 skips all of Sunday and the first two hours of Monday
+        let saturday = Utc.with_ymd_and_hms(2017, 1, 7, 23, 30, 0).unwrap();
+        assert!(!hints.is_skipped(saturday));
+        assert_eq!(
+            hints.next_allowed(saturday + Duration::hours(1)),
+            Utc.with_ymd_and_hms(2017, 1, 9, 2, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_scheduler_due() {
+        let start = Utc.with_ymd_and_hms(2017, 1, 2, 12, 0, 0).unwrap();
+        let mut scheduler = Scheduler::new();
+        let mut tracker = HealthTracker::new();
+        let mut slow = Channel::default();
+        slow.set_ttl("360".to_string());
+        scheduler.update("slow", &slow);
+        scheduler.update("fast", &Channel::default());
+        scheduler.add("new");
+        scheduler.set_observed_interval("busy", Some(Duration::minutes(1)));
+
+        assert_eq!(scheduler.interval("slow"), Duration::hours(6));
+        assert_eq!(scheduler.interval("fast"), DEFAULT_POLL_INTERVAL);
+        assert_eq!(scheduler.interval("busy"), Duration::minutes(15));
+
+        // Hints asking for longer intervals than the maximum are cut short
+        let mut rare = Channel::default();
+        rare.set_ttl("4320".to_string());
+        scheduler.update("rare", &rare);
+        assert_eq!(scheduler.interval("rare"), Duration::days(1));
+        scheduler.remove("rare");
+
+        for url in ["slow", "fast", "busy"] {
+            tracker.record_success(url, start);
+        }
+        assert_eq!(scheduler.due(&tracker, start), vec!["new"]);
+        assert_eq!(
+            scheduler.due(&tracker, start + Duration::hours(1)),
+            vec!["new", "busy", "fast"]
+        );
+
+        // Failing feeds back off
+        tracker.record_failure("fast", "HTTP 503", start + Duration::hours(1));
+        assert_eq!(
+            scheduler.next_due("fast", &tracker),
+            Some(start + Duration::hours(3))
+        );
+    }
+}
@@ -0,0 +1,307 @@
+//! Definition and implementation of feed sources other than HTTP: local files, `data:` URLs, bytes and readers.
+
+// std imports
+use std::{error::Error, fs, io::Read, path::PathBuf};
+
+// third-party imports
+use rss::Channel;
+use url::Url;
+
+// Local Imports
+use super::body::LimitedReader;
+use super::client::FeedClient;
+use super::compression::{decompress_detected, Compression};
+use super::config::FetchConfig;
+use super::diagnostics::Diagnostics;
+use super::error::FetchError;
+use super::fetcher::{FetchResponse, Fetcher};
+use super::functions::{read_channel, read_decompressed_channel};
+
+/// Where to load a feed from.
+pub enum FeedSource<'a> {
+    /// An `http://` or `https://` URL, a `file://` URL, a `data:` URL or a plain filesystem path.
+    Location(String),
+    /// A reader over the raw feed, e.g. the bytes of a feed or stdin.
+    Reader(Box<dyn Read + 'a>),
+}
+
+/// Function implementations for FeedSource.
+impl<'a> FeedSource<'a> {
+    /// Create a FeedSource reading the feed from the given reader.
+    pub fn from_reader(reader: impl Read + 'a) -> FeedSource<'a> {
+        FeedSource::Reader(Box::new(reader))
+    }
+}
+
+impl From<&str> for FeedSource<'_> {
+    fn from(location: &str) -> Self {
+        FeedSource::Location(location.to_string())
+    }
+}
+
+impl From<String> for FeedSource<'_> {
+    fn from(location: String) -> Self {
+        FeedSource::Location(location)
+    }
+}
+
+impl<'a> From<&'a [u8]> for FeedSource<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        FeedSource::from_reader(bytes)
+    }
+}
+
+/// Load and parse the feed from the given source, fetching HTTP URLs with the default FetchConfig.
+/// Feeds are sanitized and transcoded the same way regardless of where they come from.
+/// Local files and `data:` URLs are limited to the default body size, like HTTP responses.
+pub fn load_channel<'a>(source: impl Into<FeedSource<'a>>) -> Result<Channel, Box<dyn Error>> {
+    match source.into() {
+        FeedSource::Location(location) if is_local(&location) => {
+            load_location(&location, FetchConfig::default().body_limit())
+        }
+        FeedSource::Location(location) => {
+            FeedClient::new(FetchConfig::default())?.get_channel(&location)
+        }
+        FeedSource::Reader(reader) => read_channel(reader, None),
+    }
+}
+
+/// Same as `load_channel`, fetching HTTP URLs with the given Fetcher. Local locations are always read directly.
+pub fn load_channel_with<'a, F: Fetcher + ?Sized>(
+    fetcher: &F,
+    source: impl Into<FeedSource<'a>>,
+) -> Result<Channel, Box<dyn Error>> {
+    match source.into() {
+        FeedSource::Location(location) if is_local(&location) => {
+            load_location(&location, FetchConfig::default().body_limit())
+        }
+        FeedSource::Location(location) => Ok(fetcher.fetch_channel(&location)?.into_channel()),
+        FeedSource::Reader(reader) => read_channel(reader, None),
+    }
+}
+
+/// Load and parse the feed at the given local location, failing with `FetchError::BodyTooLarge` past the given limit.
+fn load_location(location: &str, limit: Option<u64>) -> Result<Channel, Box<dyn Error>> {
+    let (body, content_type, _) = open_local(location)?.into_parts();
+    let (body, _) = decompress_detected(body)?;
+    let mut body = LimitedReader::new(body, limit);
+    let channel = read_decompressed_channel(
+        &mut body,
+        content_type.as_deref(),
+        &mut Diagnostics::new(""),
+    );
+    match limit {
+        Some(limit) if body.exceeded() => Err(Box::new(FetchError::BodyTooLarge {
+            url: location.to_string(),
+            limit,
+        })),
+        _ => channel,
+    }
+}
+
+/// Return whether the given location is read locally rather than fetched over HTTP.
+pub(crate) fn is_local(location: &str) -> bool {
+    let lower = location.trim_start().to_lowercase();
+    !(lower.starts_with("http://") || lower.starts_with("https://"))
+}
+
+/// Open the `file://` URL, `data:` URL or filesystem path at the given location.
+/// Failures are reported as a `FetchError::Transport`, like failed HTTP requests.
+fn open_local(location: &str) -> Result<FetchResponse<'static>, Box<dyn Error>> {
+    let error = |message: String| -> Box<dyn Error> {
+        Box::new(FetchError::Transport {
+            url: location.to_string(),
+            message,
+            retryable: false,
+        })
+    };
+    let trimmed = location.trim();
+    if trimmed
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+    {
+        let (contents, content_type) = parse_data_url(trimmed).map_err(error)?;
+        return Ok(FetchResponse::new(std::io::Cursor::new(contents))
+            .content_type(content_type.as_deref()));
+    }
+    let path = if trimmed
+        .get(..7)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file://"))
+    {
+        Url::parse(trimmed)
+            .ok()
+            .and_then(|url| url.to_file_path().ok())
+            .ok_or_else(|| error(String::from("invalid file URL")))?
+    } else {
+        PathBuf::from(trimmed)
+    };
+    let file = fs::File::open(&path).map_err(|e| error(e.to_string()))?;
+    // Other formats are detected from their contents, brotli has no magic number to detect it by
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    match name.as_deref().and_then(Compression::from_extension) {
+        Some(Compression::Brotli) => Ok(FetchResponse::new(
+            Compression::Brotli
+                .decoder(file)
+                .map_err(|e| error(e.to_string()))?,
+        )),
+        _ => Ok(FetchResponse::new(file)),
+    }
+}
+
+/// Decode the given `data:[<mediatype>][;base64],<data>` URL into its contents and media type.
+fn parse_data_url(url: &str) -> Result<(Vec<u8>, Option<String>), String> {
+    let (header, data) = url[5..]
+        .split_once(',')
+        .ok_or_else(|| String::from("data URL without a comma"))?;
+    let (media_type, base64) = match header.strip_suffix(";base64") {
+        Some(media_type) => (media_type, true),
+        None => (header, false),
+    };
+    let data = percent_decode(data);
+    let contents = if base64 {
+        base64_decode(&data).ok_or_else(|| String::from("invalid base64 in data URL"))?
+    } else {
+        data
+    };
+    let media_type = Some(media_type.trim())
+        .filter(|media_type| !media_type.is_empty())
+        .map(percent_decode_str);
+    Ok((contents, media_type))
+}
+
+/// Decode the `%XX` escapes in the given string.
+fn percent_decode(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Decode the `%XX` escapes in the given string, replacing invalid UTF-8.
+pub(crate) fn percent_decode_str(input: &str) -> String {
+    String::from_utf8_lossy(&percent_decode(input)).into_owned()
+}
+
+/// Decode standard or URL-safe base64, ignoring whitespace and padding.
+fn base64_decode(input: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(input.len() * 3 / 4);
+    let mut buffer = 0u32;
+    let mut bits = 0;
+    for &byte in input {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' | b' ' | b'\t' | b'\r' | b'\n' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits) as u8);
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetching::fixture::FixtureFetcher;
+    use crate::fetching::functions::get_channels;
+    use std::{env, fs};
+
+    #[test]
+    /// Test wether load_channel() loads feeds from paths, compressed files, file URLs, data URLs, bytes and readers
+    fn test_load_channel() {
+        let path = "./resources/testing/example.rss";
+        assert_eq!(load_channel(path).unwrap().items().len(), 3);
+
+        let absolute = env::current_dir().unwrap().join(path);
+        let file_url = Url::from_file_path(&absolute).unwrap();
+        assert_eq!(load_channel(file_url.as_str()).unwrap().items().len(), 3);
+
+        // Local locations are only read by load_channel, never by a FeedClient
+        let results = get_channels(&[path, file_url.as_str()]);
+        assert!(results.iter().all(|result| matches!(
+            result.as_ref().unwrap_err().downcast_ref::<FetchError>(),
+            Some(FetchError::UnsupportedScheme { .. })
+        )));
+
+        let bytes = fs::read(path).unwrap();
+        assert_eq!(load_channel(bytes.as_slice()).unwrap().items().len(), 3);
+        let file = fs::File::open(path).unwrap();
+        let channel = load_channel(FeedSource::from_reader(file)).unwrap();
+        assert_eq!(channel.items().len(), 3);
+
+        for compressed in ["example.rss.gz", "example.rss.br", "example.rss.zst"] {
+            let path = format!("./resources/testing/{}", compressed);
+            assert_eq!(load_channel(path).unwrap().items().len(), 3);
+        }
+
+        let data = "data:application/rss+xml,%3Crss%3E%3Cchannel%3E%3Ctitle%3ED%C3%A4ta%3C/title%3E%3C/channel%3E%3C/rss%3E";
+        assert_eq!(load_channel(data).unwrap().title(), "Däta");
+        // PHJzcz48Y2hhbm5lbD48dGl0bGU+QjY0PC90aXRsZT48L2NoYW5uZWw+PC9yc3M+ is <rss><channel><title>B64</title></channel></rss>
+        let data = "data:;base64,PHJzcz48Y2hhbm5lbD48dGl0bGU+QjY0PC90aXRsZT48L2NoYW5uZWw+PC9yc3M+";
+        assert_eq!(load_channel(data).unwrap().title(), "B64");
+    }
+
+    #[test]
+    /// Test wether local sources fail with a FetchError and HTTP sources go through the given Fetcher
+    fn test_load_channel_with() {
+        let error = load_channel("./resources/testing/missing.rss").unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::Transport { .. })
+        ));
+        assert!(load_channel("data:text/xml").is_err());
+
+        let data = format!(
+            "data:,<rss><channel><title>{}</title></channel></rss>",
+            "x".repeat(64)
+        );
+        let error = load_location(&data, Some(32)).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { limit: 32, .. })
+        ));
+        assert!(load_location(&data, Some(1024)).is_ok());
+        // The limit applies to the decompressed feed, the compressed file is smaller than it
+        let error = load_location("./resources/testing/example.rss.gz", Some(1024)).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<FetchError>(),
+            Some(FetchError::BodyTooLarge { limit: 1024, .. })
+        ));
+
+        let fetcher = FixtureFetcher::new()
+            .file("https://example.com/rss", "./resources/testing/example.rss")
+            .unwrap();
+        let channel = load_channel_with(&fetcher, "https://example.com/rss").unwrap();
+        assert_eq!(channel.items().len(), 3);
+        let channel = load_channel_with(&fetcher, "./resources/testing/example.rss").unwrap();
+        assert_eq!(channel.items().len(), 3);
+        assert_eq!(fetcher.requests(), ["https://example.com/rss"]);
+    }
+}
@@ -1,9 +1,48 @@
 //! FeRSS Library
 mod fetching;
 mod processing;
+mod storage;
 mod structures;
+mod subscriptions;
 
-pub use fetching::functions::get_channels;
+pub use fetching::auth::Credentials;
+pub use fetching::client::FeedClient;
+pub use fetching::compression::Compression;
+pub use fetching::config::{FetchConfig, DEFAULT_MAX_BODY_SIZE, DEFAULT_USER_AGENT};
+pub use fetching::diagnostics::{Diagnostics, FeedFormat, ItemCounts};
+pub use fetching::discovery::{
+    discover_feeds, discover_feeds_with, find_feed_links, DiscoverySource, FeedCandidate, FeedKind,
+};
+pub use fetching::encoding::{detect_encoding, DetectedEncoding, EncodingSource};
+pub use fetching::error::FetchError;
+pub use fetching::fetched::FetchedChannel;
+pub use fetching::fetcher::{FetchResponse, Fetcher};
+pub use fetching::fixture::FixtureFetcher;
+pub use fetching::functions::{get_channels, get_channels_with};
+pub use fetching::health::{FeedHealth, HealthTracker};
+pub use fetching::rate_limit::RateLimit;
+pub use fetching::redirect::{Redirect, SubscriptionUpdate};
+pub use fetching::retry::RetryPolicy;
+pub use fetching::scheduler::{RefreshHints, Scheduler, DEFAULT_POLL_INTERVAL};
+pub use fetching::source::{load_channel, load_channel_with, FeedSource};
 pub use processing::enums;
+pub use processing::functions::{item_id, item_text};
+pub use processing::html::{char_count, html_to_text, sanitize_html, summarize, word_count};
+pub use processing::namespaces::normalize_namespaces;
+pub use processing::urls::{resolve_html_urls, resolve_urls};
+pub use storage::feed_store::{FeedStore, ItemQuery, PublishTimes};
+pub use storage::json_store::JsonFileStore;
+pub use storage::memory_store::MemoryStore;
+pub use structures::category::Category;
 pub use structures::channel_collection::ChannelCollection;
+pub use structures::item_state::ItemState;
+pub use structures::media::{Thumbnail, ThumbnailSource};
+pub use structures::podcast::{
+    Chapters, Funding, Person, PodcastEpisode, PodcastShow, SafeEnclosure, Transcript,
+};
 pub use structures::safe_item::SafeItem;
+pub use subscriptions::folder::Folder;
+pub use subscriptions::opml::{
+    parse_opml, parse_opml_folders, write_opml, write_opml_with_folders,
+};
+pub use subscriptions::subscription::Subscription;
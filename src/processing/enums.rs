@@ -6,10 +6,14 @@ pub enum ItemSortType {
     Title,
     /// Sort by the item's date.
     Date,
-    /// Sort by the item's length.
+    /// Sort by the item's length, in characters of the plain text of its description.
     Length,
+    /// Sort by the item's length, in words of the plain text of its description.
+    Words,
     /// Sort by the item's source
     Source,
+    /// Sort by the item's podcast episode duration, items without one first.
+    Duration,
 }
 
 /// Defines how an ItemCollection should be filtered.
@@ -18,8 +22,25 @@ pub enum ItemFilterType {
     Title(String),
     /// Filter by the item's date. Ensuring that the date matches the string
     Date(String),
-    /// Filter by the item's length. Ensuring that the description is smaller than usize
+    /// Filter by the item's length. Ensuring that the plain text of the description has at most usize characters
     Length(usize),
+    /// Filter by the item's length. Ensuring that the plain text of the description has at most usize words
+    Words(usize),
     /// Filter by the item's source. Ensuring that the source matches the string
     Source(String),
+    /// Filter by the item's state. Ensuring that the item hasn't been read.
+    Unread,
+    /// Filter by the item's state. Ensuring that the item has been starred.
+    Starred,
+    /// Filter by the item's state. Ensuring that the item hasn't been hidden.
+    Visible,
+    /// Filter by the item's folder. Ensuring that its channel is filed under the folder path, or any of its sub-folders.
+    Folder(Vec<String>),
+    /// Filter by the item's tags. Ensuring that the item or its channel is tagged with the string, ignoring case.
+    Tagged(String),
+    /// Filter by the item's categories. Ensuring that the item has a category with the name, ignoring case,
+    /// and with the domain when one is given.
+    Category(String, Option<String>),
+    /// Filter by the item's enclosure. Ensuring that the item has an audio enclosure.
+    HasAudio,
 }
@@ -0,0 +1,91 @@
+//! Set of functions for processing rss items.
+
+// third-party imports
+use rss::Item;
+
+// Local Imports
+use super::html::html_to_text;
+
+/// Return a stable identifier for the given item.
+/// Uses the guid when present, falls back to the link and finally to a hash of the title, date and description.
+pub fn item_id(item: &Item) -> String {
+    if let Some(guid) = item.guid() {
+        if !guid.value().is_empty() {
+            return guid.value().to_string();
+        }
+    }
+    if let Some(link) = item.link() {
+        if !link.is_empty() {
+            return link.to_string();
+        }
+    }
+    let mut hash = Fnv1a::new();
+    hash.write(item.title().unwrap_or_default());
+    hash.write(item.pub_date().unwrap_or_default());
+    hash.write(item.description().unwrap_or_default());
+    format!("{:016x}", hash.finish())
+}
+
+/// Return the plain text of the given item, extracted from its description or, without one, its `content:encoded`.
+pub fn item_text(item: &Item) -> Option<String> {
+    item.description()
+        .or_else(|| item.content())
+        .map(html_to_text)
+}
+
+/// Minimal FNV-1a hasher, used because its output is stable across Rust versions.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Fnv1a {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, value: &str) {
+        for byte in value.bytes().chain(std::iter::once(0)) {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rss::Guid;
+
+    use super::*;
+
+    #[test]
+    /// Test that item_id prefers the guid, then the link, then a hash
+    fn test_item_id() {
+        let mut item = Item::default();
+        item.set_title(String::from("a"));
+        let hashed = item_id(&item);
+        assert_eq!(hashed.len(), 16);
+        // The hash is deterministic
+        assert_eq!(hashed, item_id(&item.clone()));
+
+        item.set_link(String::from("https://example.com/a"));
+        assert_eq!(item_id(&item), "https://example.com/a");
+
+        let mut guid = Guid::default();
+        guid.set_value(String::from("1.15789882"));
+        item.set_guid(guid);
+        assert_eq!(item_id(&item), "1.15789882");
+    }
+
+    #[test]
+    /// Test that item_text prefers the description and strips its markup
+    fn test_item_text() {
+        let mut item = Item::default();
+        assert_eq!(item_text(&item), None);
+        item.set_content(String::from("<p>Full <em>content</em></p>"));
+        assert_eq!(item_text(&item).as_deref(), Some("Full content"));
+        item.set_description(String::from("<p>Short&hellip;</p>"));
+        assert_eq!(item_text(&item).as_deref(), Some("Short…"));
+    }
+}
@@ -0,0 +1,612 @@
+//! Set of functions for sanitizing the HTML of item descriptions and content, and extracting their plain text.
+
+// Local Imports
+use crate::fetching::entities::html_entity;
+
+/// The tags kept by `sanitize_html`, along with the attributes kept on them besides `title`, `lang` and `dir`.
+const ALLOWED_TAGS: [(&str, &[&str]); 47] = [
+    ("a", &["href", "target"]),
+    ("abbr", &[]),
+    ("b", &[]),
+    ("blockquote", &["cite"]),
+    ("br", &[]),
+    ("caption", &[]),
+    ("cite", &[]),
+    ("code", &[]),
+    ("dd", &[]),
+    ("del", &["datetime"]),
+    ("div", &[]),
+    ("dl", &[]),
+    ("dt", &[]),
+    ("em", &[]),
+    ("figcaption", &[]),
+    ("figure", &[]),
+    ("h1", &[]),
+    ("h2", &[]),
+    ("h3", &[]),
+    ("h4", &[]),
+    ("h5", &[]),
+    ("h6", &[]),
+    ("hr", &[]),
+    ("i", &[]),
+    ("img", &["src", "alt", "width", "height"]),
+    ("ins", &["datetime"]),
+    ("kbd", &[]),
+    ("li", &["value"]),
+    ("mark", &[]),
+    ("ol", &["start", "reversed"]),
+    ("p", &[]),
+    ("pre", &[]),
+    ("q", &["cite"]),
+    ("s", &[]),
+    ("small", &[]),
+    ("span", &[]),
+    ("strong", &[]),
+    ("sub", &[]),
+    ("sup", &[]),
+    ("table", &[]),
+    ("tbody", &[]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan", "scope"]),
+    ("thead", &[]),
+    ("time", &["datetime"]),
+    ("tr", &[]),
+    ("ul", &[]),
+];
+
+/// The attributes kept on every allowed tag.
+const GLOBAL_ATTRIBUTES: [&str; 3] = ["title", "lang", "dir"];
+
+/// The tags which are dropped along with everything inside them.
+const DROPPED_TAGS: [&str; 13] = [
+    "script", "style", "iframe", "frame", "frameset", "object", "embed", "applet", "noscript",
+    "template", "svg", "math", "textarea",
+];
+
+/// The tags which never have content or a closing tag.
+const VOID_TAGS: [&str; 3] = ["br", "hr", "img"];
+
+/// The tags which separate the text around them, e.g. paragraphs and line breaks.
+const BLOCK_TAGS: [&str; 30] = [
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "td",
+    "th",
+    "tr",
+    "ul",
+];
+
+/// The attributes holding a url.
+const URL_ATTRIBUTES: [&str; 3] = ["href", "src", "cite"];
+
+/// The schemes allowed in urls, relative urls are always allowed.
+const ALLOWED_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Url fragments of well-known tracking pixels and feed analytics images.
+const TRACKERS: [&str; 6] = [
+    "feeds.feedburner.com/~r/",
+    "feeds.feedburner.com/~ff/",
+    "feedsportal.com",
+    "pixel.wp.com",
+    "stats.wordpress.com",
+    "/~r/",
+];
+
+/// A start tag of an allowed element, with its kept attributes.
+struct StartTag {
+    name: String,
+    attributes: Vec<(String, String)>,
+    self_closing: bool,
+}
+
+/// Sanitize the given HTML so it can be embedded in a page as it is.
+/// Only an allowlist of formatting tags and attributes is kept, scripts, styles, frames, embedded objects,
+/// event handlers and tracking pixels are removed, and urls other than http, https, mailto and relative ones are dropped.
+/// Links get `rel="noopener noreferrer"`. Unclosed elements are closed and stray `<` and `&` are escaped.
+pub fn sanitize_html(html: &str) -> String {
+    let mut sanitized = String::with_capacity(html.len());
+    let mut open: Vec<String> = Vec::new();
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map(|end| &after[end + 3..]).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map(|end| &rest[end + 1..]).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').map(|end| end + 1).unwrap_or(after.len());
+            let name = tag_name(&after[..end]);
+            if let Some(position) = open.iter().rposition(|open| *open == name) {
+                for name in open.drain(position..).rev() {
+                    sanitized.push_str(&format!("</{}>", name));
+                }
+            }
+            rest = &after[end..];
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (tag, after) = parse_start_tag(&rest[1..]);
+            rest = after;
+            if DROPPED_TAGS.contains(&tag.name.as_str()) {
+                if !tag.self_closing {
+                    rest = skip_element(rest, &tag.name);
+                }
+                continue;
+            }
+            if let Some(tag) = allow(tag) {
+                write_start_tag(&mut sanitized, &tag);
+                if !tag.self_closing && !VOID_TAGS.contains(&tag.name.as_str()) {
+                    open.push(tag.name);
+                } else if !VOID_TAGS.contains(&tag.name.as_str()) {
+                    sanitized.push_str(&format!("</{}>", tag.name));
+                }
+            }
+        } else {
+            let end = text_len(rest);
+            sanitized.push_str(&escape_text(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    for name in open.into_iter().rev() {
+        sanitized.push_str(&format!("</{}>", name));
+    }
+    sanitized
+}
+
+/// Extract the plain text of the given HTML, as a reader would see it.
+/// Tags are stripped, along with the contents of scripts, styles and the other dropped elements,
+/// entities are decoded and runs of whitespace, including the breaks between blocks, become a single space.
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            rest = after.find("-->").map(|end| &after[end + 3..]).unwrap_or("");
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            rest = rest.find('>').map(|end| &rest[end + 1..]).unwrap_or("");
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let end = after.find('>').map(|end| end + 1).unwrap_or(after.len());
+            if BLOCK_TAGS.contains(&tag_name(&after[..end]).as_str()) {
+                text.push(' ');
+            }
+            rest = &after[end..];
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (tag, after) = parse_start_tag(&rest[1..]);
+            rest = after;
+            if DROPPED_TAGS.contains(&tag.name.as_str()) {
+                if !tag.self_closing {
+                    rest = skip_element(rest, &tag.name);
+                }
+            } else if BLOCK_TAGS.contains(&tag.name.as_str()) {
+                text.push(' ');
+            }
+        } else {
+            let end = text_len(rest);
+            text.push_str(&decode_entities(&rest[..end]));
+            rest = &rest[end..];
+        }
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Return the number of characters in the given text.
+pub fn char_count(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Return the number of words in the given text, separated by whitespace.
+pub fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// Shorten the given plain text to at most `max_chars` characters, cutting at a word boundary and ending with `…`.
+/// Text which already fits is returned as it is. A single word longer than the summary is cut in the middle.
+pub fn summarize(text: &str, max_chars: usize) -> String {
+    if char_count(text) <= max_chars {
+        return text.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let cut = text
+        .char_indices()
+        .nth(max_chars - 1)
+        .map(|(end, _)| end)
+        .unwrap_or(text.len());
+    // Cut at the last space if the limit falls inside a word
+    let summary = match text[cut..].starts_with(char::is_whitespace) {
+        true => &text[..cut],
+        false => text[..cut]
+            .rfind(char::is_whitespace)
+            .map(|end| &text[..end])
+            .unwrap_or(&text[..cut]),
+    };
+    let summary = summary.trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation());
+    format!("{}…", summary)
+}
+
+/// Return the url of the first image in the given HTML which isn't a tracking pixel or smaller than `min_size` pixels
+/// along either published dimension, along with its published width and height.
+pub(crate) fn first_image(html: &str, min_size: u32) -> Option<(String, Option<u32>, Option<u32>)> {
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let (tag, after) = parse_start_tag(rest);
+        rest = after;
+        if tag.name != "img" {
+            continue;
+        }
+        let attribute = |name: &str| {
+            tag.attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .map(|(_, value)| value.trim())
+        };
+        let dimension = |name: &str| {
+            attribute(name).and_then(|value| value.trim_end_matches("px").parse::<u32>().ok())
+        };
+        let (width, height) = (dimension("width"), dimension("height"));
+        let src = match attribute("src") {
+            Some(src) if !src.is_empty() && !src.starts_with("data:") && is_safe_url(src) => src,
+            _ => continue,
+        };
+        let small = [width, height]
+            .into_iter()
+            .flatten()
+            .any(|size| size < min_size);
+        if !small && !TRACKERS.iter().any(|tracker| src.contains(tracker)) {
+            return Some((src.to_string(), width, height));
+        }
+    }
+    None
+}
+
+/// Return the length of the text at the start of the given string, up to the next `<` after its first character.
+fn text_len(rest: &str) -> usize {
+    let first = rest.chars().next().map(char::len_utf8).unwrap_or(0);
+    rest[first..]
+        .find('<')
+        .map(|end| end + first)
+        .unwrap_or(rest.len())
+}
+
+/// Return the lowercase name at the start of the given tag contents.
+fn tag_name(contents: &str) -> String {
+    contents
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == ':')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+/// Parse the start tag at the start of the given string, which follows its `<`.
+/// Returns the tag along with the rest of the string after its `>`.
+fn parse_start_tag(contents: &str) -> (StartTag, &str) {
+    let name = tag_name(contents);
+    let mut rest = &contents[name.len()..];
+    let mut attributes = Vec::new();
+    let mut self_closing = false;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+        if rest.is_empty() {
+            break;
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            self_closing = contents[..contents.len() - rest.len()]
+                .trim_end()
+                .ends_with('/');
+            rest = after;
+            break;
+        }
+        let name_len = rest
+            .find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len())
+            .max(1);
+        let attribute = rest[..name_len].to_ascii_lowercase();
+        rest = rest[name_len..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, remaining) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => match after[1..].find(quote) {
+                    Some(end) => (&after[1..end + 1], &after[end + 2..]),
+                    None => (&after[1..], ""),
+                },
+                _ => {
+                    let end = after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len());
+                    (&after[..end], &after[end..])
+                }
+            };
+            value = decode_entities(raw);
+            rest = remaining;
+        }
+        attributes.push((attribute, value));
+    }
+    (
+        StartTag {
+            name,
+            attributes,
+            self_closing,
+        },
+        rest,
+    )
+}
+
+/// Skip everything up to and including the closing tag of the element with the given name.
+fn skip_element<'a>(rest: &'a str, name: &str) -> &'a str {
+    let lower = rest.to_ascii_lowercase();
+    let closing = format!("</{}", name);
+    match lower.find(&closing) {
+        Some(start) => rest[start..]
+            .find('>')
+            .map(|end| &rest[start + end + 1..])
+            .unwrap_or(""),
+        None => "",
+    }
+}
+
+/// Return the given start tag with only its allowed attributes, or `None` if the tag isn't allowed or is a tracking pixel.
+fn allow(tag: StartTag) -> Option<StartTag> {
+    let allowed = ALLOWED_TAGS
+        .iter()
+        .find(|(name, _)| *name == tag.name)
+        .map(|(_, attributes)| *attributes)?;
+    let mut attributes: Vec<(String, String)> = tag
+        .attributes
+        .into_iter()
+        .filter(|(name, _)| {
+            allowed.contains(&name.as_str()) || GLOBAL_ATTRIBUTES.contains(&name.as_str())
+        })
+        .filter(|(name, value)| !URL_ATTRIBUTES.contains(&name.as_str()) || is_safe_url(value))
+        .collect();
+    if tag.name == "img" {
+        let src = attributes.iter().find(|(name, _)| name == "src")?;
+        let dimension = |name: &str| {
+            attributes
+                .iter()
+                .find(|(attribute, _)| attribute == name)
+                .and_then(|(_, value)| value.trim().trim_end_matches("px").parse::<u32>().ok())
+        };
+        let pixel =
+            matches!(dimension("width"), Some(0 | 1)) || matches!(dimension("height"), Some(0 | 1));
+        if pixel || TRACKERS.iter().any(|tracker| src.1.contains(tracker)) {
+            return None;
+        }
+    }
+    if tag.name == "a" {
+        attributes.retain(|(name, value)| name != "target" || value == "_blank");
+        attributes.push((String::from("rel"), String::from("noopener noreferrer")));
+    }
+    Some(StartTag {
+        name: tag.name,
+        attributes,
+        self_closing: tag.self_closing,
+    })
+}
+
+/// Return whether the given url is relative or uses an allowed scheme.
+pub(crate) fn is_safe_url(url: &str) -> bool {
+    // Browsers ignore whitespace and control characters in schemes, e.g. `java\tscript:`
+    let url: String = url
+        .chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect();
+    match url.find([':', '/', '?', '#']) {
+        Some(position) if url[position..].starts_with(':') => {
+            let scheme = url[..position].to_ascii_lowercase();
+            ALLOWED_SCHEMES.contains(&scheme.as_str())
+        }
+        _ => true,
+    }
+}
+
+/// Write the given start tag, escaping its attribute values.
+fn write_start_tag(sanitized: &mut String, tag: &StartTag) {
+    sanitized.push('<');
+    sanitized.push_str(&tag.name);
+    for (name, value) in &tag.attributes {
+        sanitized.push_str(&format!(" {}=\"{}\"", name, escape_attribute(value)));
+    }
+    sanitized.push('>');
+}
+
+/// Escape the given text, keeping character and entity references but escaping stray `&`, `<` and `>`.
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for (i, c) in text.char_indices() {
+        match c {
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '&' if !starts_with_reference(&text[i..]) => escaped.push_str("&amp;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape the given attribute value.
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Return whether the given string starts with a character or entity reference, e.g. `&amp;` or `&#169;`.
+fn starts_with_reference(text: &str) -> bool {
+    let reference = match text[1..].find(';') {
+        Some(end) if end > 0 && end <= 32 => &text[1..end + 1],
+        _ => return false,
+    };
+    match reference.strip_prefix('#') {
+        Some(number) => match number.strip_prefix(['x', 'X']) {
+            Some(hex) => !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit()),
+            None => !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()),
+        },
+        None => reference.chars().all(|c| c.is_ascii_alphanumeric()),
+    }
+}
+
+/// Decode the character references and the named entities in the given text or attribute value,
+/// so urls are checked the way a browser would read them.
+fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(position) = rest.find('&') {
+        decoded.push_str(&rest[..position]);
+        rest = &rest[position..];
+        let reference = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 32)
+            .map(|end| &rest[1..end + 1]);
+        let characters = reference.and_then(|reference| match reference.strip_prefix('#') {
+            Some(number) => {
+                let code_point = match number.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => number.parse().ok()?,
+                };
+                char::from_u32(code_point).map(String::from)
+            }
+            None => html_entity(reference).map(str::to_string),
+        });
+        match (reference, characters) {
+            (Some(reference), Some(characters)) => {
+                decoded.push_str(&characters);
+                rest = &rest[reference.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_html() {
+        let html = "<p onclick=\"steal()\" style=\"color:red\">Hello <b>world</b><script>alert(1)</script></p>\
+            <iframe src=\"https://evil.com\"></iframe><!-- comment --><a href=\"https://example.com/?a=1&amp;b=2\" target=\"_blank\" rel=\"opener\">link</a>";
+        assert_eq!(
+            sanitize_html(html),
+            "<p>Hello <b>world</b></p><a href=\"https://example.com/?a=1&amp;b=2\" target=\"_blank\" rel=\"noopener noreferrer\">link</a>"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_html_urls() {
+        assert_eq!(
+            sanitize_html("<a href=\"javascript:alert(1)\">a</a>"),
+            "<a rel=\"noopener noreferrer\">a</a>"
+        );
+        assert_eq!(
+            sanitize_html("<a href=\"jav&#x09;ascript:alert(1)\">a</a>"),
+            "<a rel=\"noopener noreferrer\">a</a>"
+        );
+        assert_eq!(
+            sanitize_html(
+                "<img src=\"data:image/png;base64,AAAA\"><img src=/a.png alt='A \"quoted\" image'>"
+            ),
+            "<img src=\"/a.png\" alt=\"A &quot;quoted&quot; image\">"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_html_tracking_pixels() {
+        let html = "<img src=\"https://example.com/pixel.gif\" width=\"1\" height=\"1\">\
+            <img src=\"http://feeds.feedburner.com/~r/example/~4/abc\">\
+            <img src=\"https://example.com/photo.jpg\" width=\"640\">";
+        assert_eq!(
+            sanitize_html(html),
+            "<img src=\"https://example.com/photo.jpg\" width=\"640\">"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_html_malformed() {
+        assert_eq!(
+            sanitize_html("<div><p>unclosed <i>tags & 1 < 2</div> <unknown>x</unknown></p>"),
+            "<div><p>unclosed <i>tags &amp; 1 &lt; 2</i></p></div> x"
+        );
+        assert_eq!(
+            sanitize_html("a<br/>b<br>c &copy; <style>p{}"),
+            "a<br>b<br>c &copy; "
+        );
+        assert_eq!(sanitize_html("été <b>2024</b>"), "été <b>2024</b>");
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<p>Caf&eacute; &amp; <b>bar</b>&nbsp;&#8212;  open</p><p>daily<br>from 9<script>track()</script></p>\
+            <ul><li>one</li><li>two</li></ul>";
+        let text = html_to_text(html);
+        assert_eq!(text, "Café & bar — open daily from 9 one two");
+        assert_eq!(char_count(&text), 38);
+        assert_eq!(word_count(&text), 10);
+        assert_eq!(
+            html_to_text("Plain text, no markup"),
+            "Plain text, no markup"
+        );
+    }
+
+    #[test]
+    fn test_summarize() {
+        let text = "The quick brown fox jumps over the lazy dog.";
+        assert_eq!(summarize(text, 100), text);
+        assert_eq!(summarize(text, 16), "The quick brown…");
+        assert_eq!(summarize(text, 15), "The quick…");
+        assert_eq!(summarize("Überlänge", 5), "Über…");
+        assert_eq!(summarize(text, 20), "The quick brown fox…");
+    }
+
+    #[test]
+    fn test_first_image() {
+        let html = "<p><img src=\"https://example.com/icon.png\" width=\"16\" height=\"16\">\
+            <img src=\"https://feeds.feedburner.com/~r/example/~4/abc\">\
+            <img src=\"https://example.com/photo.jpg?w=640&amp;h=480\" width=\"640\"></p>";
+        assert_eq!(
+            first_image(html, 100),
+            Some((
+                String::from("https://example.com/photo.jpg?w=640&h=480"),
+                Some(640),
+                None
+            ))
+        );
+        assert_eq!(first_image("<p>No images</p>", 100), None);
+    }
+}
@@ -1,2 +1,6 @@
 //! Sorting and Filtering module.
 pub mod enums;
+pub mod functions;
+pub mod html;
+pub mod namespaces;
+pub mod urls;
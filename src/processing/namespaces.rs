@@ -0,0 +1,145 @@
+//! Set of functions for normalizing the namespace prefixes of channel and item extensions.
+
+// std imports
+use std::collections::BTreeMap;
+
+// third-party imports
+use rss::extension::{Extension, ExtensionMap};
+use rss::Channel;
+
+/// The namespaces whose extensions are looked up by prefix, with the prefix they are stored under.
+const KNOWN_NAMESPACES: [(&str, &str); 3] = [
+    ("atom", "http://www.w3.org/2005/Atom"),
+    ("media", "http://search.yahoo.com/mrss/"),
+    ("podcast", "https://podcastindex.org/namespace/1.0"),
+];
+
+/// Store the extensions of the given channel and its items from well-known namespaces under their usual prefix,
+/// whatever prefix the feed declared the namespace with, e.g. `<m:thumbnail>` under `media` for `xmlns:m="http://search.yahoo.com/mrss/"`.
+/// A usual prefix declared for another namespace is renamed out of the way, e.g. to `media2`.
+/// The namespace declarations and the names of the elements are renamed along, so the channel is written out consistently.
+pub fn normalize_namespaces(channel: &mut Channel) {
+    let renames = prefix_renames(channel.namespaces());
+    if renames.is_empty() {
+        return;
+    }
+    let namespaces: BTreeMap<String, String> = channel
+        .namespaces()
+        .iter()
+        .map(|(prefix, uri)| (renamed(&renames, prefix).to_string(), uri.clone()))
+        .collect();
+    channel.set_namespaces(namespaces);
+    let extensions = rename_extensions(channel.extensions(), &renames);
+    channel.set_extensions(extensions);
+    for item in channel.items_mut() {
+        let extensions = rename_extensions(item.extensions(), &renames);
+        item.set_extensions(extensions);
+    }
+}
+
+/// Return the declared prefixes which have to be renamed, with their new prefix.
+fn prefix_renames(namespaces: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    let mut renames = BTreeMap::new();
+    for (prefix, uri) in namespaces {
+        let known = KNOWN_NAMESPACES
+            .iter()
+            .find(|(_, known)| same_namespace(known, uri));
+        match known {
+            Some((usual, _)) if usual != prefix => {
+                renames.insert(prefix.clone(), usual.to_string());
+            }
+            None if KNOWN_NAMESPACES.iter().any(|(usual, _)| usual == prefix) => {
+                let free = (2..)
+                    .map(|n| format!("{}{}", prefix, n))
+                    .find(|free| !namespaces.contains_key(free))
+                    .unwrap_or_default();
+                renames.insert(prefix.clone(), free);
+            }
+            _ => {}
+        }
+    }
+    renames
+}
+
+/// Return whether the two namespace URIs are the same, ignoring a trailing slash some feeds leave out.
+fn same_namespace(a: &str, b: &str) -> bool {
+    a.trim().trim_end_matches('/') == b.trim().trim_end_matches('/')
+}
+
+/// Return the new prefix of the given prefix.
+fn renamed<'a>(renames: &'a BTreeMap<String, String>, prefix: &'a str) -> &'a str {
+    renames.get(prefix).map(String::as_str).unwrap_or(prefix)
+}
+
+/// Return the given extensions with their prefixes renamed, merging the elements of prefixes renamed to the same one.
+fn rename_extensions(
+    extensions: &ExtensionMap,
+    renames: &BTreeMap<String, String>,
+) -> ExtensionMap {
+    let mut renamed_extensions = ExtensionMap::new();
+    for (prefix, elements) in extensions {
+        let merged = renamed_extensions
+            .entry(renamed(renames, prefix).to_string())
+            .or_default();
+        for (name, elements) in elements {
+            let elements = elements
+                .iter()
+                .map(|element| rename_element(element, renames));
+            merged.entry(name.clone()).or_default().extend(elements);
+        }
+    }
+    renamed_extensions
+}
+
+/// Return the given element with the prefix of its name and of its children's names renamed.
+fn rename_element(element: &Extension, renames: &BTreeMap<String, String>) -> Extension {
+    let mut element = element.clone();
+    if let Some((prefix, local_name)) = element.name.split_once(':') {
+        element.name = format!("{}:{}", renamed(renames, prefix), local_name);
+    }
+    for children in element.children.values_mut() {
+        for child in children.iter_mut() {
+            *child = rename_element(child, renames);
+        }
+    }
+    element
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::structures::media::Thumbnail;
+
+    #[test]
+    fn test_normalize_namespaces() {
+        let feed = "<rss xmlns:m=\"http://search.yahoo.com/mrss\" xmlns:media=\"https://example.com/other\" \
+            xmlns:a=\"http://www.w3.org/2005/Atom\"><channel><title>Feed</title>\
+            <a:link rel=\"self\" href=\"https://example.com/feed\"/>\
+            <item><m:group><m:thumbnail url=\"https://example.com/a.jpg\"/></m:group><media:rating>5</media:rating></item>\
+            </channel></rss>";
+        let mut channel = Channel::read_from(feed.as_bytes()).unwrap();
+        normalize_namespaces(&mut channel);
+        let namespaces: Vec<_> = channel
+            .namespaces()
+            .iter()
+            .map(|(prefix, uri)| (prefix.as_str(), uri.as_str()))
+            .collect();
+        assert_eq!(
+            namespaces,
+            [
+                ("atom", "http://www.w3.org/2005/Atom"),
+                ("media", "http://search.yahoo.com/mrss"),
+                ("media2", "https://example.com/other")
+            ]
+        );
+        assert_eq!(channel.extensions()["atom"]["link"][0].name(), "atom:link");
+        let extensions = channel.items()[0].extensions();
+        let group = &extensions["media"]["group"][0];
+        assert_eq!(group.name(), "media:group");
+        assert_eq!(group.children()["thumbnail"][0].name(), "media:thumbnail");
+        assert_eq!(extensions["media2"]["rating"][0].value(), Some("5"));
+        assert!(!extensions.contains_key("m"));
+        let thumbnail = Thumbnail::of_item(&channel.items()[0]).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/a.jpg");
+    }
+}
@@ -0,0 +1,256 @@
+//! Set of functions for resolving the relative urls of channels and items.
+
+// std imports
+use std::sync::OnceLock;
+
+// third-party imports
+use regex::{Captures, Regex};
+use rss::{Channel, Guid};
+use url::{ParseError, Url};
+
+// Local Imports
+use super::functions::item_id;
+
+/// The attributes of HTML tags holding urls which are resolved.
+const URL_ATTRIBUTES: [&str; 4] = ["href", "src", "cite", "poster"];
+
+/// Resolve the relative urls of the given channel and its items, in place.
+/// Item links, comments, enclosure urls and the `href`, `src`, `cite` and `poster` attributes in descriptions
+/// and `content:encoded` are resolved against the document's `xml:base` if there is one, otherwise against the channel link,
+/// otherwise against the url the feed was fetched from. The channel link itself is resolved against `xml:base` or the fetch url.
+/// Urls which can't be resolved are left as they are.
+/// Items without a guid whose `item_id` would change get their original id as a guid which isn't a permalink,
+/// so read states keep matching them.
+pub fn resolve_urls(channel: &mut Channel, xml_base: Option<&str>, fetch_url: Option<&str>) {
+    let fetch_url = fetch_url.and_then(|url| Url::parse(url).ok());
+    let xml_base = xml_base.and_then(|base| join(fetch_url.as_ref(), base));
+    let document_base = xml_base.clone().or(fetch_url);
+    if let Some(link) = resolve(document_base.as_ref(), channel.link()) {
+        channel.set_link(link);
+    }
+    if let Some(mut image) = channel.image().cloned() {
+        if let Some(url) = resolve(document_base.as_ref(), image.url()) {
+            image.set_url(url);
+            channel.set_image(image);
+        }
+    }
+    let base = xml_base
+        .or_else(|| join(document_base.as_ref(), channel.link()))
+        .or(document_base);
+    let base = match base {
+        Some(base) => base,
+        None => return,
+    };
+    for item in channel.items_mut() {
+        let id = item_id(item);
+        if let Some(link) = item.link().and_then(|link| resolve(Some(&base), link)) {
+            item.set_link(link);
+        }
+        if let Some(comments) = item.comments().and_then(|url| resolve(Some(&base), url)) {
+            item.set_comments(comments);
+        }
+        if let Some(mut enclosure) = item.enclosure().cloned() {
+            if let Some(url) = resolve(Some(&base), enclosure.url()) {
+                enclosure.set_url(url);
+                item.set_enclosure(enclosure);
+            }
+        }
+        if let Some(description) = item.description() {
+            let description = resolve_html_urls(description, &base);
+            item.set_description(description);
+        }
+        if let Some(content) = item.content() {
+            let content = resolve_html_urls(content, &base);
+            item.set_content(content);
+        }
+        if item.guid().is_none_or(|guid| guid.value().is_empty()) && item_id(item) != id {
+            item.set_guid(Guid {
+                value: id,
+                permalink: false,
+            });
+        }
+    }
+}
+
+/// Resolve the relative urls in the `href`, `src`, `cite` and `poster` attributes of the given HTML against the given base,
+/// leaving the rest of the markup as it is.
+pub fn resolve_html_urls(html: &str, base: &Url) -> String {
+    static TAG_RE: OnceLock<Regex> = OnceLock::new();
+    static ATTRIBUTE_RE: OnceLock<Regex> = OnceLock::new();
+    let tag_re =
+        TAG_RE.get_or_init(|| Regex::new(r#"<[a-zA-Z](?:[^>"']|"[^"]*"|'[^']*')*>"#).unwrap());
+    // Every attribute is matched, so the values of other attributes are skipped over as a whole
+    let attribute_re = ATTRIBUTE_RE.get_or_init(|| {
+        Regex::new(r#"(\s([^\s"'<>/=]+)\s*=\s*)(?:"([^"]*)"|'([^']*)'|([^\s"'>]+))"#).unwrap()
+    });
+    tag_re
+        .replace_all(html, |tag: &Captures| {
+            attribute_re
+                .replace_all(&tag[0], |attribute: &Captures| {
+                    let name = &attribute[2];
+                    if !URL_ATTRIBUTES
+                        .iter()
+                        .any(|url| name.eq_ignore_ascii_case(url))
+                    {
+                        return attribute[0].to_string();
+                    }
+                    let (quote, value) = match (attribute.get(3), attribute.get(4)) {
+                        (Some(value), _) => ("\"", value.as_str()),
+                        (_, Some(value)) => ("'", value.as_str()),
+                        _ => ("", &attribute[5]),
+                    };
+                    match resolve(Some(base), value) {
+                        Some(resolved) => {
+                            format!("{}{}{}{}", &attribute[1], quote, resolved, quote)
+                        }
+                        None => attribute[0].to_string(),
+                    }
+                })
+                .into_owned()
+        })
+        .into_owned()
+}
+
+/// Resolve the given url against the given base if it's relative.
+/// Returns `None` for absolute urls, in-page fragments and urls which can't be resolved.
+fn resolve(base: Option<&Url>, url: &str) -> Option<String> {
+    let url = url.trim();
+    if url.is_empty() || url.starts_with('#') {
+        return None;
+    }
+    match Url::parse(url) {
+        Err(ParseError::RelativeUrlWithoutBase) => base?.join(url).ok().map(String::from),
+        _ => None,
+    }
+}
+
+/// Parse the given url, resolving it against the given base if it's relative.
+fn join(base: Option<&Url>, url: &str) -> Option<Url> {
+    let url = url.trim();
+    if url.is_empty() {
+        return None;
+    }
+    match Url::parse(url) {
+        Err(ParseError::RelativeUrlWithoutBase) => base?.join(url).ok(),
+        parsed => parsed.ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rss::{Enclosure, Item};
+
+    fn channel(link: &str) -> Channel {
+        let mut item = Item::default();
+        item.set_link(String::from("posts/1.html"));
+        item.set_comments(String::from("/comments/1"));
+        let mut enclosure = Enclosure::default();
+        enclosure.set_url("../media/1.mp3");
+        item.set_enclosure(enclosure);
+        item.set_description(String::from(
+            "<p><a href=\"/about\">About</a> <img src='img/a.png' alt=\"a\"> \
+            <a href=\"#notes\">Notes</a> <a href=https://other.com/>Other</a></p>",
+        ));
+        let mut channel = Channel::default();
+        channel.set_link(link.to_string());
+        channel.set_items(vec![item]);
+        channel
+    }
+
+    #[test]
+    fn test_resolve_urls_channel_link() {
+        let mut channel = channel("https://example.com/blog/");
+        resolve_urls(
+            &mut channel,
+            None,
+            Some("https://feeds.example.net/feed.xml"),
+        );
+        let item = &channel.items()[0];
+        assert_eq!(item.link(), Some("https://example.com/blog/posts/1.html"));
+        assert_eq!(item.comments(), Some("https://example.com/comments/1"));
+        assert_eq!(
+            item.enclosure().unwrap().url(),
+            "https://example.com/media/1.mp3"
+        );
+        assert_eq!(
+            item.description(),
+            Some(
+                "<p><a href=\"https://example.com/about\">About</a> <img src='https://example.com/blog/img/a.png' alt=\"a\"> \
+                <a href=\"#notes\">Notes</a> <a href=https://other.com/>Other</a></p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_resolve_html_urls_quoted_values() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let html =
+            "<img alt=\"see src=x.png > here\" title='href=y' SRC=\"a.png\" data-src=\"b.png\">";
+        assert_eq!(
+            resolve_html_urls(html, &base),
+            "<img alt=\"see src=x.png > here\" title='href=y' SRC=\"https://example.com/blog/a.png\" data-src=\"b.png\">"
+        );
+    }
+
+    #[test]
+    fn test_resolve_urls_stable_ids() {
+        let mut channel = channel("https://example.com/blog/");
+        let mut described = Item::default();
+        described.set_description(String::from("<a href=\"/about\">About</a>"));
+        let mut guided = Item::default();
+        guided.set_guid(Guid {
+            value: String::from("1"),
+            permalink: false,
+        });
+        guided.set_link(String::from("posts/2.html"));
+        let mut items = channel.items().to_vec();
+        items.extend([described, guided]);
+        channel.set_items(items);
+        let ids: Vec<String> = channel.items().iter().map(item_id).collect();
+        resolve_urls(&mut channel, None, None);
+        assert_eq!(
+            channel.items()[0].link(),
+            Some("https://example.com/blog/posts/1.html")
+        );
+        let resolved: Vec<String> = channel.items().iter().map(item_id).collect();
+        assert_eq!(resolved, ids);
+        assert_eq!(resolved[0], "posts/1.html");
+        assert!(!channel.items()[0].guid().unwrap().is_permalink());
+        assert_eq!(channel.items()[2].guid().unwrap().value(), "1");
+    }
+
+    #[test]
+    fn test_resolve_urls_xml_base() {
+        let mut channel = channel("/blog/");
+        resolve_urls(
+            &mut channel,
+            Some("/archive/2024/"),
+            Some("https://example.com/feed.xml"),
+        );
+        assert_eq!(channel.link(), "https://example.com/blog/");
+        assert_eq!(
+            channel.items()[0].link(),
+            Some("https://example.com/archive/2024/posts/1.html")
+        );
+    }
+
+    #[test]
+    fn test_resolve_urls_fetch_url() {
+        let mut channel = channel("");
+        resolve_urls(
+            &mut channel,
+            None,
+            Some("https://example.com/feeds/main.xml"),
+        );
+        assert_eq!(
+            channel.items()[0].link(),
+            Some("https://example.com/feeds/posts/1.html")
+        );
+
+        // Without any base the urls are left as they are
+        let mut channel = self::channel("/blog/");
+        resolve_urls(&mut channel, None, None);
+        assert_eq!(channel.items()[0].link(), Some("posts/1.html"));
+    }
+}
@@ -0,0 +1,157 @@
+//! Definition of the storage trait and the queries it answers.
+
+// std imports
+use std::{collections::HashMap, error::Error};
+
+// third-party imports
+use chrono::{DateTime, Utc};
+use rss::{Channel, Item};
+
+// Local Imports
+use crate::enums::{ItemFilterType, ItemSortType};
+use crate::structures::item_collection::ItemCollection;
+use crate::structures::item_state::ItemState;
+use crate::subscriptions::subscription::Subscription;
+
+/// When the items seen in a channel were published, keyed by item id.
+pub type PublishTimes = HashMap<String, DateTime<Utc>>;
+
+/// A query for items in a FeedStore.
+/// Filters are applied in order, followed by the sort and finally the page.
+#[derive(Default)]
+pub struct ItemQuery {
+    filters: Vec<ItemFilterType>,
+    sort: Option<ItemSortType>,
+    offset: usize,
+    limit: Option<usize>,
+}
+
+/// Function implementations for ItemQuery.
+impl ItemQuery {
+    /// Create a new query matching every item.
+    pub fn new() -> ItemQuery {
+        ItemQuery::default()
+    }
+
+    /// Add a filter to the query.
+    pub fn filter(mut self, filter_type: ItemFilterType) -> ItemQuery {
+        self.filters.push(filter_type);
+        self
+    }
+
+    /// Sort the matching items.
+    pub fn sort(mut self, sort_type: ItemSortType) -> ItemQuery {
+        self.sort = Some(sort_type);
+        self
+    }
+
+    /// Only return `limit` items, starting at `offset`.
+    pub fn page(mut self, offset: usize, limit: usize) -> ItemQuery {
+        self.offset = offset;
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Run the query against the given items, each along with the subscription of its feed if known.
+    /// The subscriptions are used for folder and tag filters, the given item states for state filters.
+    pub fn apply<'a>(
+        self,
+        items: impl IntoIterator<Item = (&'a Item, Option<&'a Subscription>)>,
+        states: &'a HashMap<String, ItemState>,
+    ) -> Vec<&'a Item> {
+        let mut collection = ItemCollection::with_states(states);
+        for (item, subscription) in items {
+            match subscription {
+                Some(subscription) => collection.push_from(item, subscription),
+                None => collection.push(item),
+            }
+        }
+        for filter_type in self.filters {
+            collection.filter(filter_type);
+        }
+        if let Some(sort_type) = self.sort {
+            collection.sort(sort_type);
+        }
+        let items = collection.items().into_iter().skip(self.offset);
+        match self.limit {
+            Some(limit) => items.take(limit).collect(),
+            None => items.collect(),
+        }
+    }
+}
+
+/// Persistence backend for channels, their items and publish times, the user's subscriptions, folders and item state.
+/// Channels are keyed by their feed url, items by their id.
+pub trait FeedStore {
+    /// Save a channel under the given feed url, replacing any channel previously stored there.
+    fn save_channel(&mut self, url: &str, channel: &Channel) -> Result<(), Box<dyn Error>>;
+
+    /// Load every stored channel along with its feed url.
+    fn load_channels(&self) -> Result<Vec<(String, Channel)>, Box<dyn Error>>;
+
+    /// Remove the channel stored under the given feed url. Returns whether a channel was removed.
+    fn remove_channel(&mut self, url: &str) -> Result<bool, Box<dyn Error>>;
+
+    /// Insert new items into the channel stored under the given feed url, updating the ones that already exist.
+    /// Returns the number of newly inserted items.
+    fn upsert_items(&mut self, url: &str, items: &[Item]) -> Result<usize, Box<dyn Error>>;
+
+    /// Replace the stored subscriptions with the given ones.
+    fn save_subscriptions(&mut self, subscriptions: &[Subscription]) -> Result<(), Box<dyn Error>>;
+
+    /// Load every stored subscription.
+    fn load_subscriptions(&self) -> Result<Vec<Subscription>, Box<dyn Error>>;
+
+    /// Replace the stored folder paths with the given ones.
+    fn save_folders(&mut self, folders: &[Vec<String>]) -> Result<(), Box<dyn Error>>;
+
+    /// Load every stored folder path.
+    fn load_folders(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>>;
+
+    /// Replace the stored publish times of the items seen in every channel, keyed by feed url and then by item id.
+    fn save_publish_times(
+        &mut self,
+        times: &HashMap<String, PublishTimes>,
+    ) -> Result<(), Box<dyn Error>>;
+
+    /// Load the stored publish times of the items seen in every channel, keyed by feed url and then by item id.
+    fn load_publish_times(&self) -> Result<HashMap<String, PublishTimes>, Box<dyn Error>>;
+
+    /// Return the state of the item with the given id.
+    fn item_state(&self, id: &str) -> Result<ItemState, Box<dyn Error>>;
+
+    /// Set the state of the item with the given id.
+    fn set_item_state(&mut self, id: &str, state: ItemState) -> Result<(), Box<dyn Error>>;
+
+    /// Set the states of several items at once, keyed by item id.
+    /// Stores writing on every change should override this to write once.
+    fn set_item_states(
+        &mut self,
+        states: &HashMap<String, ItemState>,
+    ) -> Result<(), Box<dyn Error>> {
+        for (id, state) in states {
+            self.set_item_state(id, state.clone())?;
+        }
+        Ok(())
+    }
+
+    /// Load the state of every item that has one, keyed by item id.
+    fn item_states(&self) -> Result<HashMap<String, ItemState>, Box<dyn Error>>;
+
+    /// Return clones of the stored items matching the query.
+    fn query(&self, query: ItemQuery) -> Result<Vec<Item>, Box<dyn Error>> {
+        let channels = self.load_channels()?;
+        let subscriptions = self.load_subscriptions()?;
+        let states = self.item_states()?;
+        let items = query.apply(
+            channels.iter().flat_map(|(url, channel)| {
+                let subscription = subscriptions
+                    .iter()
+                    .find(|subscription| subscription.url() == url);
+                channel.items().iter().map(move |item| (item, subscription))
+            }),
+            &states,
+        );
+        Ok(items.into_iter().cloned().collect())
+    }
+}
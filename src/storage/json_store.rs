@@ -0,0 +1,177 @@
+//! Definition and implementation of the JSON file store.
+
+// std imports
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+};
+
+// third-party imports
+use rss::{Channel, Item};
+
+// Local Imports
+use super::feed_store::{FeedStore, PublishTimes};
+use super::memory_store::MemoryStore;
+use crate::structures::item_state::ItemState;
+use crate::subscriptions::subscription::Subscription;
+
+/// A FeedStore persisting its contents to a single JSON file.
+/// The whole file is rewritten after every change.
+pub struct JsonFileStore {
+    path: PathBuf,
+    store: MemoryStore,
+}
+
+/// Function implementations for JsonFileStore.
+impl JsonFileStore {
+    /// Open the store at the given path. The file is created on the first change if it doesn't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<JsonFileStore, Box<dyn Error>> {
+        let path = path.as_ref().to_path_buf();
+        let store = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            MemoryStore::new()
+        };
+        Ok(JsonFileStore { path, store })
+    }
+
+    /// Return the path of the underlying file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the contents of the store to a temporary file and move it over the previous one.
+    fn flush(&self) -> Result<(), Box<dyn Error>> {
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(&self.store)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl FeedStore for JsonFileStore {
+    fn save_channel(&mut self, url: &str, channel: &Channel) -> Result<(), Box<dyn Error>> {
+        self.store.save_channel(url, channel)?;
+        self.flush()
+    }
+
+    fn load_channels(&self) -> Result<Vec<(String, Channel)>, Box<dyn Error>> {
+        self.store.load_channels()
+    }
+
+    fn remove_channel(&mut self, url: &str) -> Result<bool, Box<dyn Error>> {
+        let removed = self.store.remove_channel(url)?;
+        if removed {
+            self.flush()?;
+        }
+        Ok(removed)
+    }
+
+    fn upsert_items(&mut self, url: &str, items: &[Item]) -> Result<usize, Box<dyn Error>> {
+        let inserted = self.store.upsert_items(url, items)?;
+        self.flush()?;
+        Ok(inserted)
+    }
+
+    fn save_subscriptions(&mut self, subscriptions: &[Subscription]) -> Result<(), Box<dyn Error>> {
+        self.store.save_subscriptions(subscriptions)?;
+        self.flush()
+    }
+
+    fn load_subscriptions(&self) -> Result<Vec<Subscription>, Box<dyn Error>> {
+        self.store.load_subscriptions()
+    }
+
+    fn save_folders(&mut self, folders: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        self.store.save_folders(folders)?;
+        self.flush()
+    }
+
+    fn load_folders(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        self.store.load_folders()
+    }
+
+    fn save_publish_times(
+        &mut self,
+        times: &HashMap<String, PublishTimes>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.store.save_publish_times(times)?;
+        self.flush()
+    }
+
+    fn load_publish_times(&self) -> Result<HashMap<String, PublishTimes>, Box<dyn Error>> {
+        self.store.load_publish_times()
+    }
+
+    fn item_state(&self, id: &str) -> Result<ItemState, Box<dyn Error>> {
+        self.store.item_state(id)
+    }
+
+    fn set_item_state(&mut self, id: &str, state: ItemState) -> Result<(), Box<dyn Error>> {
+        self.store.set_item_state(id, state)?;
+        self.flush()
+    }
+
+    fn set_item_states(
+        &mut self,
+        states: &HashMap<String, ItemState>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.store.set_item_states(states)?;
+        self.flush()
+    }
+
+    fn item_states(&self) -> Result<HashMap<String, ItemState>, Box<dyn Error>> {
+        self.store.item_states()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    /// Test that the contents of the store survive reopening the file
+    fn test_json_store_roundtrip() {
+        let path = env::temp_dir().join("ferss_test_json_store_roundtrip.json");
+        let _ = fs::remove_file(&path);
+
+        let mut store = JsonFileStore::open(&path).unwrap();
+        let mut channel = Channel::default();
+        channel.set_title("Channel 1".to_string());
+        let mut item = Item::default();
+        item.set_title("a".to_string());
+        channel.set_items(vec![item]);
+        store
+            .save_channel("https://example.com/feed", &channel)
+            .unwrap();
+        let mut state = ItemState::new();
+        state.set_starred(true);
+        store.set_item_state("a", state).unwrap();
+        let mut states = HashMap::new();
+        let mut state = ItemState::new();
+        state.set_hidden(true);
+        states.insert(String::from("b"), state);
+        store.set_item_states(&states).unwrap();
+        let mut subscription = Subscription::new("https://example.com/feed");
+        subscription.add_tag("rust");
+        store.save_subscriptions(&[subscription.clone()]).unwrap();
+        store.save_folders(&[vec![String::from("Empty")]]).unwrap();
+
+        let store = JsonFileStore::open(&path).unwrap();
+        let channels = store.load_channels().unwrap();
+        assert_eq!(channels.len(), 1);
+        assert_eq!(channels[0].0, "https://example.com/feed");
+        assert_eq!(channels[0].1.title(), "Channel 1");
+        assert_eq!(channels[0].1.items().len(), 1);
+        assert!(store.item_state("a").unwrap().starred());
+        assert!(store.item_state("b").unwrap().hidden());
+        assert_eq!(store.load_subscriptions().unwrap(), [subscription]);
+        assert_eq!(store.load_folders().unwrap(), [vec!["Empty"]]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
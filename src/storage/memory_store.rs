@@ -0,0 +1,256 @@
+//! Definition and implementation of the in-memory store.
+
+// std imports
+use std::{collections::HashMap, error::Error};
+
+// third-party imports
+use rss::{Channel, Item};
+use serde::{Deserialize, Serialize};
+
+// Local Imports
+use super::feed_store::{FeedStore, PublishTimes};
+use crate::processing::functions::item_id;
+use crate::structures::item_state::ItemState;
+use crate::subscriptions::subscription::Subscription;
+
+/// A FeedStore keeping everything in memory. Nothing is persisted once it is dropped.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct MemoryStore {
+    channels: Vec<(String, Channel)>,
+    #[serde(default)]
+    subscriptions: Vec<Subscription>,
+    #[serde(default)]
+    folders: Vec<Vec<String>>,
+    #[serde(default)]
+    publish_times: HashMap<String, PublishTimes>,
+    states: HashMap<String, ItemState>,
+}
+
+/// Function implementations for MemoryStore.
+impl MemoryStore {
+    /// Create a new empty MemoryStore.
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    fn channel_mut(&mut self, url: &str) -> Option<&mut Channel> {
+        self.channels
+            .iter_mut()
+            .find(|(channel_url, _)| channel_url == url)
+            .map(|(_, channel)| channel)
+    }
+}
+
+impl FeedStore for MemoryStore {
+    fn save_channel(&mut self, url: &str, channel: &Channel) -> Result<(), Box<dyn Error>> {
+        match self.channel_mut(url) {
+            Some(stored) => *stored = channel.clone(),
+            None => self.channels.push((url.to_string(), channel.clone())),
+        }
+        Ok(())
+    }
+
+    fn load_channels(&self) -> Result<Vec<(String, Channel)>, Box<dyn Error>> {
+        Ok(self.channels.clone())
+    }
+
+    fn remove_channel(&mut self, url: &str) -> Result<bool, Box<dyn Error>> {
+        let len = self.channels.len();
+        self.channels.retain(|(channel_url, _)| channel_url != url);
+        Ok(self.channels.len() != len)
+    }
+
+    /// Items for a url that has no channel yet are stored in a new empty channel.
+    /// New items are appended after the existing ones.
+    fn upsert_items(&mut self, url: &str, items: &[Item]) -> Result<usize, Box<dyn Error>> {
+        if self.channel_mut(url).is_none() {
+            self.channels.push((url.to_string(), Channel::default()));
+        }
+        let channel = self.channel_mut(url).unwrap();
+        let mut stored = channel.items().to_vec();
+        let mut inserted = 0;
+        for item in items {
+            let id = item_id(item);
+            match stored.iter_mut().find(|stored| item_id(stored) == id) {
+                Some(existing) => *existing = item.clone(),
+                None => {
+                    stored.push(item.clone());
+                    inserted += 1;
+                }
+            }
+        }
+        channel.set_items(stored);
+        Ok(inserted)
+    }
+
+    fn save_subscriptions(&mut self, subscriptions: &[Subscription]) -> Result<(), Box<dyn Error>> {
+        self.subscriptions = subscriptions.to_vec();
+        Ok(())
+    }
+
+    fn load_subscriptions(&self) -> Result<Vec<Subscription>, Box<dyn Error>> {
+        Ok(self.subscriptions.clone())
+    }
+
+    fn save_folders(&mut self, folders: &[Vec<String>]) -> Result<(), Box<dyn Error>> {
+        self.folders = folders.to_vec();
+        Ok(())
+    }
+
+    fn load_folders(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        Ok(self.folders.clone())
+    }
+
+    fn save_publish_times(
+        &mut self,
+        times: &HashMap<String, PublishTimes>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.publish_times = times.clone();
+        Ok(())
+    }
+
+    fn load_publish_times(&self) -> Result<HashMap<String, PublishTimes>, Box<dyn Error>> {
+        Ok(self.publish_times.clone())
+    }
+
+    fn item_state(&self, id: &str) -> Result<ItemState, Box<dyn Error>> {
+        Ok(self.states.get(id).cloned().unwrap_or_default())
+    }
+
+    fn set_item_state(&mut self, id: &str, state: ItemState) -> Result<(), Box<dyn Error>> {
+        self.states.insert(id.to_string(), state);
+        Ok(())
+    }
+
+    fn item_states(&self) -> Result<HashMap<String, ItemState>, Box<dyn Error>> {
+        Ok(self.states.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::enums::{ItemFilterType, ItemSortType};
+    use crate::storage::feed_store::ItemQuery;
+
+    use super::*;
+
+    fn item(title: &str, pub_date: &str) -> Item {
+        let mut item = Item::default();
+        item.set_title(title.to_string());
+        item.set_link(format!("https://example.com/{}", title));
+        item.set_pub_date(pub_date.to_string());
+        item
+    }
+
+    #[test]
+    fn test_memory_store_channels() {
+        let mut store = MemoryStore::new();
+        let mut channel = Channel::default();
+        channel.set_title("Channel 1".to_string());
+        store
+            .save_channel("https://example.com/feed", &channel)
+            .unwrap();
+        store
+            .save_channel("https://example.com/feed", &channel)
+            .unwrap();
+        assert_eq!(store.load_channels().unwrap().len(), 1);
+
+        assert!(store.remove_channel("https://example.com/feed").unwrap());
+        assert!(!store.remove_channel("https://example.com/feed").unwrap());
+        assert_eq!(store.load_channels().unwrap().len(), 0);
+    }
+
+    #[test]
+    fn test_memory_store_upsert_items() {
+        let mut store = MemoryStore::new();
+        let url = "https://example.com/feed";
+        let items = [
+            item("a", "Sun, 01 Jan 2017 12:00:00 GMT"),
+            item("b", "Mon, 02 Jan 2017 12:00:00 GMT"),
+        ];
+        assert_eq!(store.upsert_items(url, &items).unwrap(), 2);
+
+        // Updating an existing item does not count as an insert
+        let mut updated = item("a", "Sun, 01 Jan 2017 12:00:00 GMT");
+        updated.set_description(String::from("Updated"));
+        let items = [updated, item("c", "Tue, 03 Jan 2017 12:00:00 GMT")];
+        assert_eq!(store.upsert_items(url, &items).unwrap(), 1);
+
+        let channels = store.load_channels().unwrap();
+        let items = channels[0].1.items();
+        assert_eq!(items.len(), 3);
+        assert_eq!(items[0].description(), Some("Updated"));
+    }
+
+    #[test]
+    fn test_memory_store_query() {
+        let mut store = MemoryStore::new();
+        let items = [
+            item("c", "Tue, 03 Jan 2017 12:00:00 GMT"),
+            item("a", "Sun, 01 Jan 2017 12:00:00 GMT"),
+            item("ab", "Mon, 02 Jan 2017 12:00:00 GMT"),
+        ];
+        store
+            .upsert_items("https://example.com/feed", &items)
+            .unwrap();
+
+        let query = ItemQuery::new()
+            .filter(ItemFilterType::Title(String::from("a")))
+            .sort(ItemSortType::Date);
+        let items = store.query(query).unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0].title(), Some("a"));
+
+        let query = ItemQuery::new().sort(ItemSortType::Title).page(1, 1);
+        let items = store.query(query).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title(), Some("ab"));
+
+        let mut state = ItemState::new();
+        state.set_read(true);
+        store
+            .set_item_state("https://example.com/a", state)
+            .unwrap();
+        let items = store
+            .query(ItemQuery::new().filter(ItemFilterType::Unread))
+            .unwrap();
+        assert_eq!(items.len(), 2);
+    }
+
+    #[test]
+    fn test_memory_store_query_subscriptions() {
+        let mut store = MemoryStore::new();
+        store
+            .upsert_items("https://a.com/feed", &[item("a", "")])
+            .unwrap();
+        store
+            .upsert_items("https://b.com/feed", &[item("b", "")])
+            .unwrap();
+        let mut news = Subscription::new("https://a.com/feed");
+        news.set_folder(vec![String::from("News"), String::from("Tech")]);
+        let mut other = Subscription::new("https://b.com/feed");
+        other.add_tag("rust");
+        store.save_subscriptions(&[news, other]).unwrap();
+
+        let query = ItemQuery::new().filter(ItemFilterType::Folder(vec![String::from("News")]));
+        let items = store.query(query).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title(), Some("a"));
+
+        let query = ItemQuery::new().filter(ItemFilterType::Tagged(String::from("rust")));
+        let items = store.query(query).unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title(), Some("b"));
+    }
+
+    #[test]
+    fn test_memory_store_item_state() {
+        let mut store = MemoryStore::new();
+        assert!(!store.item_state("a").unwrap().read());
+
+        let mut state = ItemState::new();
+        state.set_read(true);
+        store.set_item_state("a", state).unwrap();
+        assert!(store.item_state("a").unwrap().read());
+    }
+}
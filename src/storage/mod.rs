@@ -0,0 +1,4 @@
+//! Storage module.
+pub mod feed_store;
+pub mod json_store;
+pub mod memory_store;
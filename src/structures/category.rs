@@ -0,0 +1,131 @@
+//! Definition and implementation of the normalized categories of items.
+
+// External Imports
+use rss::Item;
+
+// Local Imports
+use super::podcast::attribute;
+
+/// A category of an item, unified between RSS `<category>`, Atom `<atom:category>` and Dublin Core `<dc:subject>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Category {
+    name: String,
+    label: String,
+    domain: Option<String>,
+}
+
+/// Function implementations for Category.
+impl Category {
+    /// Create a new Category with the given name and domain.
+    pub fn new(name: &str, domain: Option<&str>) -> Category {
+        let label = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        Category {
+            name: label.to_lowercase(),
+            label,
+            domain: domain
+                .map(str::trim)
+                .filter(|domain| !domain.is_empty())
+                .map(str::to_string),
+        }
+    }
+
+    /// Return the categories of the given item, in the order they were published and without duplicates.
+    /// Atom categories use their `term` as the name and their `scheme` as the domain.
+    pub fn of_item(item: &Item) -> Vec<Category> {
+        let rss = item
+            .categories()
+            .iter()
+            .map(|category| Category::new(category.name(), category.domain()));
+        let atom = item
+            .extensions()
+            .get("atom")
+            .and_then(|elements| elements.get("category"))
+            .into_iter()
+            .flatten()
+            .filter_map(|category| {
+                Some(Category::new(
+                    attribute(category, "term")?,
+                    attribute(category, "scheme"),
+                ))
+            });
+        let subjects = item
+            .dublin_core_ext()
+            .map(|dublin_core| dublin_core.subjects())
+            .unwrap_or_default()
+            .iter()
+            .map(|subject| Category::new(subject, None));
+        let mut categories: Vec<Category> = Vec::new();
+        for category in rss.chain(atom).chain(subjects) {
+            let duplicate = categories
+                .iter()
+                .any(|other| other.name == category.name && other.domain == category.domain);
+            if !category.name.is_empty() && !duplicate {
+                categories.push(category);
+            }
+        }
+        categories
+    }
+
+    /// Return the normalized name of the category, lowercase and with single spaces, used for matching and counting.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the name of the category as published, with single spaces.
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    /// Return the domain of the category, the taxonomy the name comes from.
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// Return whether the category has the given name, ignoring case, and the given domain if there is one.
+    pub fn matches(&self, name: &str, domain: Option<&str>) -> bool {
+        let other = Category::new(name, domain);
+        self.name == other.name && (other.domain.is_none() || self.domain == other.domain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rss::Channel;
+
+    #[test]
+    fn test_category_of_item() {
+        let feed = "<rss xmlns:atom=\"http://www.w3.org/2005/Atom\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+            <channel><title>Feed</title><item>\
+            <category domain=\"https://example.com/topics\">Rust</category><category> rust </category>\
+            <atom:category term=\"Security\" label=\"Security news\" scheme=\"https://example.com/tags\"/>\
+            <dc:subject>Open   Source</dc:subject><dc:subject>Rust</dc:subject>\
+            </item></channel></rss>";
+        let channel = Channel::read_from(feed.as_bytes()).unwrap();
+        let categories = Category::of_item(&channel.items()[0]);
+        let names: Vec<_> = categories
+            .iter()
+            .map(|category| (category.name(), category.domain()))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                ("rust", Some("https://example.com/topics")),
+                ("rust", None),
+                ("security", Some("https://example.com/tags")),
+                ("open source", None)
+            ]
+        );
+        assert_eq!(categories[3].label(), "Open Source");
+    }
+
+    #[test]
+    fn test_category_matches() {
+        let category = Category::new("Rust", Some("https://example.com/topics"));
+        assert!(category.matches("RUST", None));
+        assert!(category.matches("rust", Some("https://example.com/topics")));
+        assert!(!category.matches("rust", Some("https://example.com/tags")));
+        assert!(!category.matches("rusty", None));
+        assert!(!Category::new("Rust", None).matches("rust", Some("https://example.com/topics")));
+    }
+}
@@ -1,18 +1,45 @@
 //! Definition and implementation of the channel collection.
 
+// Standard Library Imports
+use std::{collections::HashMap, error::Error};
+
 // External Imports
+use chrono::{prelude::*, Duration};
 use rss::{Channel, Item};
 
 // Local Imports
+use super::category::Category;
 use super::item_collection::ItemCollection;
+use super::item_state::ItemState;
 use crate::enums::{ItemFilterType, ItemSortType};
+use crate::fetching::diagnostics::Diagnostics;
+use crate::fetching::fetched::FetchedChannel;
+use crate::fetching::redirect::SubscriptionUpdate;
+use crate::fetching::scheduler::{RefreshHints, Scheduler};
+use crate::processing::functions::item_id;
+use crate::storage::feed_store::{FeedStore, PublishTimes};
+use crate::subscriptions::{
+    folder::Folder,
+    opml::{parse_opml, parse_opml_folders, write_opml_with_folders},
+    subscription::Subscription,
+};
+
+/// The number of publish timestamps kept per channel.
+const MAX_PUBLISH_TIMES: usize = 100;
+
+/// The number of most recent publish timestamps the observed publish interval is derived from.
+const OBSERVED_PUBLISH_TIMES: usize = 20;
 
-/// A collection of channels.
+/// A collection of channels, each keyed by its feed url, along with the user's subscriptions, folders and item states.
 pub struct ChannelCollection {
-    channels: Vec<Channel>,
+    channels: Vec<(String, Channel)>,
+    subscriptions: Vec<Subscription>,
+    folders: Vec<Vec<String>>,
+    states: HashMap<String, ItemState>,
+    publish_times: HashMap<String, PublishTimes>,
 }
 
-impl<'a> Default for ChannelCollection {
+impl Default for ChannelCollection {
     fn default() -> Self {
         Self::new()
     }
@@ -22,28 +49,477 @@ impl<'a> Default for ChannelCollection {
 impl ChannelCollection {
     /// Create a new empty ChannelCollection.
     pub fn new() -> ChannelCollection {
-        ChannelCollection { channels: vec![] }
+        ChannelCollection {
+            channels: vec![],
+            subscriptions: vec![],
+            folders: vec![],
+            states: HashMap::new(),
+            publish_times: HashMap::new(),
+        }
+    }
+
+    /// Load a ChannelCollection from the channels, publish times, subscriptions, folders and item states in the given store.
+    pub fn load(store: &impl FeedStore) -> Result<ChannelCollection, Box<dyn Error>> {
+        let mut collection = ChannelCollection::new();
+        // Loaded before the channels, so undated items keep the time they were first seen
+        collection.publish_times = store.load_publish_times()?;
+        for (url, channel) in store.load_channels()? {
+            collection.insert(&url, channel);
+        }
+        collection.subscriptions = store.load_subscriptions()?;
+        collection.folders = store.load_folders()?;
+        collection.states = store.item_states()?;
+        Ok(collection)
+    }
+
+    /// Save every channel, publish time, subscription, folder and item state in the collection to the given store.
+    pub fn save(&self, store: &mut impl FeedStore) -> Result<(), Box<dyn Error>> {
+        for (url, channel) in &self.channels {
+            store.save_channel(url, channel)?;
+        }
+        store.save_publish_times(&self.publish_times)?;
+        store.save_subscriptions(&self.subscriptions)?;
+        store.save_folders(&self.folders)?;
+        store.set_item_states(&self.states)
     }
 
-    /// Push a new channel to the collection.
+    /// Push a new channel to the collection under a key of its own, `#1`, `#2` and so on.
+    /// A channel doesn't know the url it was fetched from and several feeds can share a link,
+    /// so use `insert` to key a channel by its feed url instead.
     pub fn push(&mut self, channel: Channel) {
-        self.channels.push(channel);
+        let mut index = self.channels.len() + 1;
+        while self.channel(&format!("#{}", index)).is_some() {
+            index += 1;
+        }
+        self.insert(&format!("#{}", index), channel);
+    }
+
+    /// Insert a channel under the given feed url, replacing any channel already stored there.
+    pub fn insert(&mut self, url: &str, channel: Channel) {
+        self.record_publish_times(url, &channel);
+        match self.channels.iter_mut().find(|(key, _)| key == url) {
+            Some((_, stored)) => *stored = channel,
+            None => self.channels.push((url.to_string(), channel)),
+        }
+    }
+
+    /// Insert a fetched channel under the url it was requested with, replacing any channel already stored there.
+    /// Returns the diagnostics of the fetch, with its items counted as new, updated or unchanged against the replaced channel.
+    pub fn insert_fetched(&mut self, mut fetched: FetchedChannel) -> Diagnostics {
+        fetched.compare_with(self.channel(fetched.url()));
+        let diagnostics = fetched.diagnostics().clone();
+        let url = fetched.url().to_string();
+        self.insert(&url, fetched.into_channel());
+        diagnostics
     }
 
     /// Return a reference to the channels.
     pub fn channels(&self) -> Vec<&Channel> {
         let mut channels = vec![];
-        for channel in self.channels.iter() {
+        for (_, channel) in self.channels.iter() {
             channels.push(channel);
         }
         channels
     }
 
-    fn item_collection(&self) -> ItemCollection {
-        let mut collection = ItemCollection::new();
-        for channel in &self.channels {
+    /// Return the feed urls of the channels, in the same order as `channels`.
+    pub fn urls(&self) -> Vec<&str> {
+        self.channels.iter().map(|(url, _)| url.as_str()).collect()
+    }
+
+    /// Return the channel stored under the given feed url.
+    pub fn channel(&self, url: &str) -> Option<&Channel> {
+        self.channels
+            .iter()
+            .find(|(key, _)| key == url)
+            .map(|(_, channel)| channel)
+    }
+
+    /// Add a subscription to the collection, replacing any subscription to the same feed url.
+    /// The channel itself is added once it has been fetched, using `insert`.
+    pub fn subscribe(&mut self, subscription: Subscription) {
+        match self
+            .subscriptions
+            .iter_mut()
+            .find(|stored| stored.url() == subscription.url())
+        {
+            Some(stored) => *stored = subscription,
+            None => self.subscriptions.push(subscription),
+        }
+    }
+
+    /// Return a reference to the subscriptions.
+    pub fn subscriptions(&self) -> Vec<&Subscription> {
+        self.subscriptions.iter().collect()
+    }
+
+    /// Return the subscription to the given feed url.
+    pub fn subscription(&self, url: &str) -> Option<&Subscription> {
+        self.subscriptions
+            .iter()
+            .find(|subscription| subscription.url() == url)
+    }
+
+    /// Return the subscription to the given feed url, subscribing to it if needed.
+    fn subscription_mut(&mut self, url: &str) -> &mut Subscription {
+        let index = match self.subscriptions.iter().position(|s| s.url() == url) {
+            Some(index) => index,
+            None => {
+                self.subscriptions.push(Subscription::new(url));
+                self.subscriptions.len() - 1
+            }
+        };
+        &mut self.subscriptions[index]
+    }
+
+    /// Apply a subscription update suggested by a fetch.
+    /// Moved feeds are rekeyed to their new url, replacing any channel or subscription already stored there,
+    /// and gone feeds have their subscription marked dead.
+    pub fn apply_update(&mut self, update: &SubscriptionUpdate) {
+        match update {
+            SubscriptionUpdate::Moved { from, to } => {
+                if let Some(times) = self.publish_times.remove(from) {
+                    self.publish_times.entry(to.to_string()).or_insert(times);
+                }
+                if self.channel(to).is_some() {
+                    self.channels.retain(|(url, _)| url != from);
+                } else if let Some((url, _)) = self.channels.iter_mut().find(|(url, _)| url == from)
+                {
+                    *url = to.to_string();
+                }
+                if let Some(index) = self.subscriptions.iter().position(|s| s.url() == from) {
+                    let mut subscription = self.subscriptions.remove(index);
+                    subscription.set_url(to);
+                    subscription.set_dead(false);
+                    self.subscribe(subscription);
+                }
+            }
+            SubscriptionUpdate::Gone { url } => self.subscription_mut(url).set_dead(true),
+        }
+    }
+
+    /// Create a folder at the given path, so it is kept even while it holds no subscriptions.
+    pub fn create_folder(&mut self, path: Vec<String>) {
+        if !path.is_empty() && !self.folders.contains(&path) {
+            self.folders.push(path);
+        }
+    }
+
+    /// File the subscription to the given feed url under the given folder path.
+    pub fn move_subscription(&mut self, url: &str, folder: Vec<String>) {
+        self.subscription_mut(url).set_folder(folder);
+    }
+
+    /// Rename the folder at the given path, along with the paths of everything filed under it.
+    pub fn rename_folder(&mut self, path: &[String], name: &str) {
+        if path.is_empty() {
+            return;
+        }
+        let rename = |folder: &mut Vec<String>| {
+            if folder.starts_with(path) {
+                folder[path.len() - 1] = name.to_string();
+            }
+        };
+        for folder in self.folders.iter_mut() {
+            rename(folder);
+        }
+        for subscription in self.subscriptions.iter_mut() {
+            let mut folder = subscription.folder().to_vec();
+            rename(&mut folder);
+            subscription.set_folder(folder);
+        }
+    }
+
+    /// Return the tree of folders and the subscriptions filed in them.
+    /// Channels without a subscription are filed in the root folder.
+    pub fn folder_tree(&self) -> Folder {
+        let unsubscribed: Vec<Subscription> = self
+            .urls()
+            .into_iter()
+            .filter(|url| self.subscription(url).is_none())
+            .map(Subscription::new)
+            .collect();
+        let mut subscriptions = self.subscriptions();
+        subscriptions.extend(unsubscribed.iter());
+        Folder::tree(&self.folders, &subscriptions)
+    }
+
+    /// Tag the channel under the given feed url.
+    pub fn tag_channel(&mut self, url: &str, tag: &str) {
+        self.subscription_mut(url).add_tag(tag);
+    }
+
+    /// Remove the given tag from the channel under the given feed url.
+    pub fn untag_channel(&mut self, url: &str, tag: &str) {
+        self.subscription_mut(url).remove_tag(tag);
+    }
+
+    /// Tag the item with the given id.
+    pub fn tag_item(&mut self, id: &str, tag: &str) {
+        self.states.entry(id.to_string()).or_default().add_tag(tag);
+    }
+
+    /// Remove the given tag from the item with the given id.
+    pub fn untag_item(&mut self, id: &str, tag: &str) {
+        self.states
+            .entry(id.to_string())
+            .or_default()
+            .remove_tag(tag);
+    }
+
+    /// Return every tag used on channels and items, sorted and without duplicates (ignoring case).
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .subscriptions
+            .iter()
+            .flat_map(|subscription| subscription.tags())
+            .chain(self.states.values().flat_map(|state| state.tags()))
+            .map(String::as_str)
+            .collect();
+        tags.sort_by_key(|tag| tag.to_lowercase());
+        tags.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        tags
+    }
+
+    /// Return how many items there are in every category, by normalized name regardless of domain,
+    /// sorted by the number of items and then by name.
+    pub fn category_counts(&self) -> Vec<(String, usize)> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for item in self.items() {
+            let mut names: Vec<String> = Category::of_item(item)
+                .into_iter()
+                .map(|category| category.name().to_string())
+                .collect();
+            names.sort();
+            names.dedup();
+            for name in names {
+                *counts.entry(name).or_default() += 1;
+            }
+        }
+        let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+        counts.sort_by(|(a_name, a_count), (b_name, b_count)| {
+            b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+        });
+        counts
+    }
+
+    /// Import the subscriptions and folders of the given OPML document, including folders holding no subscriptions.
+    /// Subscriptions to feeds which are already subscribed to replace the existing ones.
+    /// Returns the number of imported subscriptions.
+    pub fn import_opml(&mut self, contents: &str) -> Result<usize, Box<dyn Error>> {
+        let subscriptions = parse_opml(contents)?;
+        for folder in parse_opml_folders(contents)? {
+            self.create_folder(folder);
+        }
+        let imported = subscriptions.len();
+        for subscription in subscriptions {
+            self.subscribe(subscription);
+        }
+        Ok(imported)
+    }
+
+    /// Export the subscriptions as an OPML document, keeping their folders, also the ones holding no subscriptions.
+    /// Missing titles and website urls are taken from the fetched channels,
+    /// and channels without a subscription are added to the root folder.
+    pub fn to_opml(&self, title: &str) -> String {
+        let mut subscriptions = Vec::new();
+        for subscription in &self.subscriptions {
+            let mut subscription = subscription.clone();
+            if let Some(channel) = self.channel(subscription.url()) {
+                if subscription.title().is_empty() {
+                    subscription.set_title(channel.title());
+                }
+                if subscription.html_url().is_none() && !channel.link().is_empty() {
+                    subscription.set_html_url(Some(channel.link().to_string()));
+                }
+            }
+            subscriptions.push(subscription);
+        }
+        for (url, channel) in &self.channels {
+            if self.subscription(url).is_none() {
+                let mut subscription = Subscription::new(url);
+                subscription.set_title(channel.title());
+                if !channel.link().is_empty() {
+                    subscription.set_html_url(Some(channel.link().to_string()));
+                }
+                subscriptions.push(subscription);
+            }
+        }
+        write_opml_with_folders(title, &self.folders, &subscriptions)
+    }
+
+    /// Return the state of the item with the given id.
+    pub fn item_state(&self, id: &str) -> ItemState {
+        self.states.get(id).cloned().unwrap_or_default()
+    }
+
+    /// Set the state of the item with the given id.
+    pub fn set_item_state(&mut self, id: &str, state: ItemState) {
+        self.states.insert(id.to_string(), state);
+    }
+
+    /// Mark the item with the given id as read now.
+    pub fn mark_read(&mut self, id: &str) {
+        self.states
+            .entry(id.to_string())
+            .or_default()
+            .mark_read(Utc::now());
+    }
+
+    /// Mark the item with the given id as unread.
+    pub fn mark_unread(&mut self, id: &str) {
+        self.states
+            .entry(id.to_string())
+            .or_default()
+            .set_read(false);
+    }
+
+    /// Set whether the item with the given id is starred.
+    pub fn set_starred(&mut self, id: &str, starred: bool) {
+        self.states
+            .entry(id.to_string())
+            .or_default()
+            .set_starred(starred);
+    }
+
+    /// Set whether the item with the given id is hidden.
+    pub fn set_hidden(&mut self, id: &str, hidden: bool) {
+        self.states
+            .entry(id.to_string())
+            .or_default()
+            .set_hidden(hidden);
+    }
+
+    /// Mark every item of the channel under the given feed url published before the given date as read.
+    /// Items without a parsable date are left untouched. Returns the number of items newly marked as read.
+    pub fn mark_channel_read_before(&mut self, url: &str, before: DateTime<Utc>) -> usize {
+        let now = Utc::now();
+        let mut marked = 0;
+        if let Some((_, channel)) = self.channels.iter().find(|(key, _)| key == url) {
             for item in channel.items() {
-                collection.push(item);
+                let published = item
+                    .pub_date()
+                    .and_then(|date| DateTime::parse_from_rfc2822(date).ok());
+                let id = item_id(item);
+                if published.is_some_and(|published| published < before)
+                    && !self.item_state(&id).read()
+                {
+                    self.states.entry(id).or_default().mark_read(now);
+                    marked += 1;
+                }
+            }
+        }
+        marked
+    }
+
+    /// Return the number of unread items in the channel under the given feed url.
+    pub fn unread_count(&self, url: &str) -> usize {
+        match self.channel(url) {
+            Some(channel) => channel
+                .items()
+                .iter()
+                .filter(|item| !self.item_state(&item_id(item)).read())
+                .count(),
+            None => 0,
+        }
+    }
+
+    /// Return the number of unread items per channel, keyed by feed url.
+    pub fn unread_counts(&self) -> Vec<(&str, usize)> {
+        self.urls()
+            .into_iter()
+            .map(|url| (url, self.unread_count(url)))
+            .collect()
+    }
+
+    /// Remember when the items of the given channel were published, using the time they were first seen for undated items.
+    fn record_publish_times(&mut self, url: &str, channel: &Channel) {
+        let now = Utc::now();
+        let times = self.publish_times.entry(url.to_string()).or_default();
+        for item in channel.items() {
+            let published = item
+                .pub_date()
+                .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                .map(|date| date.with_timezone(&Utc));
+            let id = item_id(item);
+            match published {
+                Some(published) => {
+                    times.insert(id, published);
+                }
+                None => {
+                    times.entry(id).or_insert(now);
+                }
+            }
+        }
+        if times.len() > MAX_PUBLISH_TIMES {
+            let mut sorted: Vec<DateTime<Utc>> = times.values().copied().collect();
+            sorted.sort_unstable();
+            let oldest_kept = sorted[sorted.len() - MAX_PUBLISH_TIMES];
+            times.retain(|_, published| *published >= oldest_kept);
+        }
+    }
+
+    /// Return when the items seen in the channel under the given feed url were published, oldest first.
+    pub fn publish_times(&self, url: &str) -> Vec<DateTime<Utc>> {
+        let mut times: Vec<DateTime<Utc>> = self
+            .publish_times
+            .get(url)
+            .map(|times| times.values().copied().collect())
+            .unwrap_or_default();
+        times.sort_unstable();
+        times
+    }
+
+    /// Return the median time between the most recently published items of the channel under the given feed url.
+    /// Returns `None` until at least two items have been published at different times.
+    pub fn observed_interval(&self, url: &str) -> Option<Duration> {
+        let times = self.publish_times(url);
+        let recent = &times[times.len().saturating_sub(OBSERVED_PUBLISH_TIMES)..];
+        let mut gaps: Vec<Duration> = recent
+            .windows(2)
+            .map(|pair| pair[1] - pair[0])
+            .filter(|gap| *gap > Duration::zero())
+            .collect();
+        if gaps.is_empty() {
+            return None;
+        }
+        gaps.sort_unstable();
+        Some(gaps[gaps.len() / 2])
+    }
+
+    /// Return the adaptive poll interval of the channel under the given feed url, as the given Scheduler computes it:
+    /// its observed publish interval, or the Scheduler's default interval if there is none,
+    /// stretched to the interval the channel asks for and bounded by the Scheduler's minimum and maximum interval.
+    pub fn poll_interval(&self, url: &str, scheduler: &Scheduler) -> Duration {
+        let hints = self
+            .channel(url)
+            .map(RefreshHints::from_channel)
+            .unwrap_or_default();
+        scheduler.interval_with(self.observed_interval(url), &hints)
+    }
+
+    /// Update the given Scheduler with the refresh hints and observed publish intervals of every channel.
+    /// Channels whose subscription is dead are removed from the schedule.
+    pub fn schedule(&self, scheduler: &mut Scheduler) {
+        for (url, channel) in &self.channels {
+            if self.subscription(url).is_some_and(|s| s.is_dead()) {
+                scheduler.remove(url);
+                continue;
+            }
+            scheduler.update(url, channel);
+            scheduler.set_observed_interval(url, self.observed_interval(url));
+        }
+    }
+
+    fn item_collection(&self) -> ItemCollection<'_> {
+        let mut collection = ItemCollection::with_states(&self.states);
+        for (url, channel) in &self.channels {
+            let subscription = self.subscription(url);
+            for item in channel.items() {
+                match subscription {
+                    Some(subscription) => collection.push_from(item, subscription),
+                    None => collection.push(item),
+                }
             }
         }
         collection
@@ -59,7 +535,7 @@ impl ChannelCollection {
     /// This will either sort by channel properties, returning the items within in an arbitrary order
     /// or by item properties, returning the channels in an arbitrary order.
     /// This alters the actual order of the channels and items stored in the collection.
-    pub fn sort(&mut self, sort_type: ItemSortType) -> ItemCollection {
+    pub fn sort(&mut self, sort_type: ItemSortType) -> ItemCollection<'_> {
         let mut items = self.item_collection();
         items.sort(sort_type);
         items
@@ -67,7 +543,7 @@ impl ChannelCollection {
 
     /// Filter the items in the collection and return a reference to them.
     /// This does *not* remove any items from the actual collection, rather it returns a new vector containing references to the collection's items.
-    pub fn filter(&mut self, filter_type: ItemFilterType) -> ItemCollection {
+    pub fn filter(&mut self, filter_type: ItemFilterType) -> ItemCollection<'_> {
         let mut items = self.item_collection();
         items.filter(filter_type);
         items
@@ -79,7 +555,12 @@ mod tests {
 
     use rss::Source;
 
+    use crate::fetching::fetcher::Fetcher;
+    use crate::fetching::fixture::FixtureFetcher;
+    use crate::fetching::scheduler::DEFAULT_POLL_INTERVAL;
     use crate::processing::enums::{ItemFilterType, ItemSortType};
+    use crate::storage::memory_store::MemoryStore;
+    use std::fs;
 
     use super::*;
 
@@ -102,6 +583,13 @@ mod tests {
 
         assert_eq!(channel_collection.channels().len(), 2);
         assert_eq!(channel_collection.items().len(), 1);
+
+        // Channels sharing a link are kept apart
+        let mut channel3 = Channel::default();
+        channel3.set_items(vec![Item::default()]);
+        channel_collection.push(channel3);
+        assert_eq!(channel_collection.urls(), vec!["#1", "#2", "#3"]);
+        assert_eq!(channel_collection.items().len(), 2);
     }
 
     #[test]
@@ -195,6 +683,45 @@ mod tests {
         assert_eq!(items[3].source().unwrap().title(), Some("C"));
     }
 
+    #[test]
+    fn test_channel_collection_category_counts() {
+        let mut channel_collection = ChannelCollection::new();
+        let item = |title: &str, categories: &[&str]| {
+            let mut item = Item::default();
+            item.set_title(title.to_string());
+            item.set_categories(
+                categories
+                    .iter()
+                    .map(|name| {
+                        let mut category = rss::Category::default();
+                        category.set_name(*name);
+                        category
+                    })
+                    .collect::<Vec<_>>(),
+            );
+            item
+        };
+
+        let mut channel = Channel::default();
+        channel.set_items(vec![
+            item("a", &["Rust", "Security"]),
+            item("b", &["rust", " RUST "]),
+        ]);
+        channel_collection.push(channel);
+        let mut channel = Channel::default();
+        channel.set_items(vec![item("c", &["Rust", "Open Source"]), item("d", &[])]);
+        channel_collection.push(channel);
+
+        assert_eq!(
+            channel_collection.category_counts(),
+            [
+                (String::from("rust"), 3),
+                (String::from("open source"), 1),
+                (String::from("security"), 1)
+            ]
+        );
+    }
+
     #[test]
     fn test_channel_collection_filter() {
         let mut channel_collection = ChannelCollection::new();
@@ -273,4 +800,374 @@ mod tests {
         assert_eq!(channel_collection.channels().len(), 3);
         assert_eq!(channel_collection.items().len(), 4);
     }
+
+    #[test]
+    fn test_channel_collection_store() {
+        let mut store = MemoryStore::new();
+        let mut channel_collection = ChannelCollection::new();
+
+        let mut channel = Channel::default();
+        channel.set_title("Channel 1".to_string());
+        channel.set_items(vec![Item::default()]);
+        channel_collection.insert("https://example.com/feed", channel.clone());
+        // Inserting under the same url replaces the channel
+        channel_collection.insert("https://example.com/feed", channel);
+        assert_eq!(channel_collection.channels().len(), 1);
+        channel_collection.save(&mut store).unwrap();
+
+        let loaded = ChannelCollection::load(&store).unwrap();
+        assert_eq!(loaded.urls(), vec!["https://example.com/feed"]);
+        assert_eq!(loaded.items().len(), 1);
+        assert_eq!(
+            loaded.channel("https://example.com/feed").unwrap().title(),
+            "Channel 1"
+        );
+    }
+
+    #[test]
+    fn test_channel_collection_item_state() {
+        let mut channel_collection = ChannelCollection::new();
+
+        let mut items = vec![];
+        for (title, pub_date) in [
+            ("a", "Sun, 01 Jan 2017 12:00:00 GMT"),
+            ("b", "Mon, 02 Jan 2017 12:00:00 GMT"),
+            ("c", "Tue, 03 Jan 2017 12:00:00 GMT"),
+        ] {
+            let mut item = Item::default();
+            item.set_title(title.to_string());
+            item.set_link(format!("https://example.com/{}", title));
+            item.set_pub_date(pub_date.to_string());
+            items.push(item);
+        }
+        let mut channel = Channel::default();
+        channel.set_items(items);
+        channel_collection.insert("https://example.com/feed", channel);
+        assert_eq!(
+            channel_collection.unread_counts(),
+            vec![("https://example.com/feed", 3)]
+        );
+
+        channel_collection.mark_read("https://example.com/a");
+        assert!(channel_collection
+            .item_state("https://example.com/a")
+            .read_at()
+            .is_some());
+        assert_eq!(
+            channel_collection.unread_count("https://example.com/feed"),
+            2
+        );
+        assert_eq!(
+            channel_collection
+                .filter(ItemFilterType::Unread)
+                .items()
+                .len(),
+            2
+        );
+
+        channel_collection.set_starred("https://example.com/b", true);
+        assert_eq!(
+            channel_collection
+                .filter(ItemFilterType::Starred)
+                .items()
+                .len(),
+            1
+        );
+
+        channel_collection.set_hidden("https://example.com/c", true);
+        assert_eq!(
+            channel_collection
+                .filter(ItemFilterType::Visible)
+                .items()
+                .len(),
+            2
+        );
+
+        // Only b is newly marked as read, a already was and c is too recent
+        let before = Utc.with_ymd_and_hms(2017, 1, 3, 0, 0, 0).unwrap();
+        assert_eq!(
+            channel_collection.mark_channel_read_before("https://example.com/feed", before),
+            1
+        );
+        assert_eq!(
+            channel_collection.unread_count("https://example.com/feed"),
+            1
+        );
+
+        // Items which are too recent don't get a state
+        let mut item = Item::default();
+        item.set_link(String::from("https://example.com/d"));
+        item.set_pub_date(String::from("Wed, 04 Jan 2017 12:00:00 GMT"));
+        let mut channel = Channel::default();
+        channel.set_items(vec![item]);
+        channel_collection.insert("https://example.com/other", channel);
+        assert_eq!(
+            channel_collection.mark_channel_read_before("https://example.com/other", before),
+            0
+        );
+
+        // The states survive a round trip through a store
+        let mut store = MemoryStore::new();
+        channel_collection.save(&mut store).unwrap();
+        assert_eq!(store.item_states().unwrap().len(), 3);
+        let loaded = ChannelCollection::load(&store).unwrap();
+        assert_eq!(loaded.unread_count("https://example.com/feed"), 1);
+        assert!(loaded.item_state("https://example.com/b").starred());
+    }
+
+    #[test]
+    fn test_channel_collection_to_opml() {
+        let mut channel_collection = ChannelCollection::new();
+
+        let mut subscription = Subscription::new("https://example.com/feed");
+        subscription.set_folder(vec![String::from("News"), String::from("Local")]);
+        channel_collection.subscribe(subscription);
+
+        let mut channel = Channel::default();
+        channel.set_title("Example".to_string());
+        channel.set_link("https://example.com/".to_string());
+        channel_collection.insert("https://example.com/feed", channel);
+
+        let mut channel = Channel::default();
+        channel.set_title("Other".to_string());
+        channel_collection.insert("https://other.com/feed", channel);
+
+        let subscriptions = parse_opml(&channel_collection.to_opml("Export")).unwrap();
+        assert_eq!(subscriptions.len(), 2);
+        assert_eq!(subscriptions[0].title(), "Example");
+        assert_eq!(subscriptions[0].html_url(), Some("https://example.com/"));
+        assert_eq!(subscriptions[0].folder(), ["News", "Local"]);
+        assert_eq!(subscriptions[1].url(), "https://other.com/feed");
+        assert!(subscriptions[1].folder().is_empty());
+
+        // Subscriptions survive a round trip through a store
+        let mut store = MemoryStore::new();
+        channel_collection.save(&mut store).unwrap();
+        let loaded = ChannelCollection::load(&store).unwrap();
+        assert_eq!(loaded.subscriptions(), channel_collection.subscriptions());
+        assert_eq!(
+            loaded
+                .subscription("https://example.com/feed")
+                .unwrap()
+                .folder(),
+            ["News", "Local"]
+        );
+    }
+
+    #[test]
+    fn test_channel_collection_folders_and_tags() {
+        let mut channel_collection = ChannelCollection::new();
+        for (url, title) in [
+            ("https://a.com/feed", "a"),
+            ("https://b.com/feed", "b"),
+            ("https://c.com/feed", "c"),
+        ] {
+            let mut item = Item::default();
+            item.set_title(title.to_string());
+            item.set_link(format!("https://{}.com/item", title));
+            let mut channel = Channel::default();
+            channel.set_items(vec![item]);
+            channel_collection.insert(url, channel);
+        }
+        let news = vec![String::from("News")];
+        let local = vec![String::from("News"), String::from("Local")];
+        channel_collection.move_subscription("https://a.com/feed", local.clone());
+        channel_collection.move_subscription("https://b.com/feed", news.clone());
+        channel_collection.create_folder(vec![String::from("Empty")]);
+
+        // Folder filters include sub-folders
+        assert_eq!(
+            channel_collection
+                .filter(ItemFilterType::Folder(news.clone()))
+                .items()
+                .len(),
+            2
+        );
+        assert_eq!(
+            channel_collection
+                .filter(ItemFilterType::Folder(local))
+                .items()
+                .len(),
+            1
+        );
+
+        // Tag filters match both channel and item tags
+        channel_collection.tag_channel("https://a.com/feed", "rust");
+        channel_collection.tag_item("https://c.com/item", "Rust");
+        channel_collection.tag_item("https://b.com/item", "later");
+        let tagged = channel_collection.filter(ItemFilterType::Tagged(String::from("RUST")));
+        assert_eq!(tagged.items().len(), 2);
+        assert_eq!(channel_collection.tags(), vec!["later", "rust"]);
+
+        channel_collection.rename_folder(&news, "Updates");
+        let tree = channel_collection.folder_tree();
+        assert_eq!(tree.urls(), ["https://c.com/feed"]);
+        assert_eq!(tree.folders()[0].name(), "Empty");
+        assert_eq!(tree.folders()[1].path(), ["Updates"]);
+        assert_eq!(tree.folders()[1].folders()[0].path(), ["Updates", "Local"]);
+
+        // Folders round-trip through OPML
+        let subscriptions = parse_opml(&channel_collection.to_opml("Export")).unwrap();
+        assert_eq!(subscriptions[0].folder(), ["Updates", "Local"]);
+        assert_eq!(subscriptions[1].folder(), ["Updates"]);
+
+        // Folders round-trip through a store
+        let mut store = MemoryStore::new();
+        channel_collection.save(&mut store).unwrap();
+        let loaded = ChannelCollection::load(&store).unwrap();
+        assert_eq!(loaded.folder_tree(), channel_collection.folder_tree());
+    }
+
+    #[test]
+    fn test_channel_collection_import_opml() {
+        let f = fs::read_to_string("./resources/testing/example.opml").unwrap();
+        let mut channel_collection = ChannelCollection::new();
+        assert_eq!(channel_collection.import_opml(&f).unwrap(), 4);
+        let tree = channel_collection.folder_tree();
+        assert_eq!(tree.folders().len(), 2);
+        assert_eq!(tree.folders()[1].name(), "Empty folder");
+
+        // Empty folders survive an export and import
+        let mut reimported = ChannelCollection::new();
+        reimported
+            .import_opml(&channel_collection.to_opml("Export"))
+            .unwrap();
+        assert_eq!(reimported.folder_tree(), tree);
+    }
+
+    #[test]
+    fn test_channel_collection_apply_update() {
+        let mut channel_collection = ChannelCollection::new();
+        channel_collection.insert("http://a.com/feed", Channel::default());
+        channel_collection.tag_channel("http://a.com/feed", "rust");
+        channel_collection.insert("https://b.com/feed", Channel::default());
+
+        channel_collection.apply_update(&SubscriptionUpdate::Moved {
+            from: String::from("http://a.com/feed"),
+            to: String::from("https://a.com/feed"),
+        });
+        assert_eq!(
+            channel_collection.urls(),
+            vec!["https://a.com/feed", "https://b.com/feed"]
+        );
+        let subscription = channel_collection.subscription("https://a.com/feed");
+        assert!(subscription.unwrap().has_tag("rust"));
+        assert!(channel_collection
+            .subscription("http://a.com/feed")
+            .is_none());
+
+        channel_collection.apply_update(&SubscriptionUpdate::Gone {
+            url: String::from("https://b.com/feed"),
+        });
+        let subscription = channel_collection.subscription("https://b.com/feed");
+        assert!(subscription.unwrap().is_dead());
+    }
+
+    #[test]
+    fn test_channel_collection_insert_fetched() {
+        let fetcher = FixtureFetcher::new()
+            .file("https://a.com/feed", "./resources/testing/example.rss")
+            .unwrap();
+        let mut channel_collection = ChannelCollection::new();
+
+        let fetched = fetcher.fetch_channel("https://a.com/feed").unwrap();
+        let diagnostics = channel_collection.insert_fetched(fetched);
+        assert_eq!(diagnostics.item_counts().unwrap().new_items(), 3);
+
+        let mut fetched = fetcher.fetch_channel("https://a.com/feed").unwrap();
+        assert_eq!(fetched.diagnostics().item_counts(), None);
+        fetched.compare_with(channel_collection.channel("https://a.com/feed"));
+        let counts = fetched.diagnostics().item_counts().unwrap();
+        assert_eq!((counts.new_items(), counts.unchanged()), (0, 3));
+        channel_collection.insert_fetched(fetched);
+        assert_eq!(channel_collection.channels().len(), 1);
+    }
+
+    #[test]
+    fn test_channel_collection_poll_interval() {
+        let start = Utc.with_ymd_and_hms(2017, 1, 2, 12, 0, 0).unwrap();
+        let channel_with_gaps = |gaps: &[i64]| {
+            let mut channel = Channel::default();
+            let items = gaps
+                .iter()
+                .enumerate()
+                .map(|(i, hours)| {
+                    let mut item = Item::default();
+                    item.set_link(format!("https://example.com/{}", i));
+                    item.set_pub_date((start + Duration::hours(*hours)).to_rfc2822());
+                    item
+                })
+                .collect::<Vec<Item>>();
+            channel.set_items(items);
+            channel
+        };
+        let mut channel_collection = ChannelCollection::new();
+        channel_collection.insert("busy", channel_with_gaps(&[0, 1, 2, 3, 5]));
+        channel_collection.insert("quiet", channel_with_gaps(&[0, 24 * 90, 24 * 180]));
+        channel_collection.insert("new", channel_with_gaps(&[0]));
+
+        assert_eq!(channel_collection.publish_times("busy").len(), 5);
+        assert_eq!(
+            channel_collection.observed_interval("busy"),
+            Some(Duration::hours(1))
+        );
+        let scheduler = Scheduler::new();
+        assert_eq!(
+            channel_collection.poll_interval("busy", &scheduler),
+            Duration::hours(1)
+        );
+        assert_eq!(
+            channel_collection.poll_interval("quiet", &scheduler),
+            Duration::days(1)
+        );
+        assert_eq!(channel_collection.observed_interval("new"), None);
+        assert_eq!(
+            channel_collection.poll_interval("new", &scheduler),
+            DEFAULT_POLL_INTERVAL
+        );
+
+        let scheduler = Scheduler::new()
+            .min_interval(Duration::hours(2))
+            .max_interval(Duration::days(30));
+        assert_eq!(
+            channel_collection.poll_interval("busy", &scheduler),
+            Duration::hours(2)
+        );
+        assert_eq!(
+            channel_collection.poll_interval("quiet", &scheduler),
+            Duration::days(30)
+        );
+
+        // New items are tracked across fetches
+        channel_collection.insert("new", channel_with_gaps(&[0, 6]));
+        assert_eq!(
+            channel_collection.observed_interval("new"),
+            Some(Duration::hours(6))
+        );
+
+        // Publish times survive a round trip through a store, also those of undated items
+        let mut undated = Channel::default();
+        undated.set_items(vec![Item::default()]);
+        channel_collection.insert("undated", undated.clone());
+        let mut store = MemoryStore::new();
+        channel_collection.save(&mut store).unwrap();
+        let mut loaded = ChannelCollection::load(&store).unwrap();
+        loaded.insert("undated", undated);
+        for url in ["busy", "quiet", "new", "undated"] {
+            assert_eq!(
+                loaded.publish_times(url),
+                channel_collection.publish_times(url)
+            );
+        }
+
+        // The Scheduler's bounds are the only ones applied
+        let mut scheduler = Scheduler::new().max_interval(Duration::days(365));
+        channel_collection.schedule(&mut scheduler);
+        assert_eq!(scheduler.interval("quiet"), Duration::days(90));
+        assert_eq!(scheduler.interval("new"), Duration::hours(6));
+        assert_eq!(
+            channel_collection.poll_interval("quiet", &scheduler),
+            scheduler.interval("quiet")
+        );
+    }
 }
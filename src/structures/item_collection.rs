@@ -1,18 +1,26 @@
 //! Definition and implementation of the item collection.
 
 // Standard Library Imports
-use std::cmp::Ordering;
+use std::collections::HashMap;
 
 // External Imports
 use chrono::prelude::*;
 use rss::Item;
 
 // Local Imports
+use super::category::Category;
+use super::item_state::ItemState;
+use super::podcast::{PodcastEpisode, SafeEnclosure};
 use crate::processing::enums::{ItemFilterType, ItemSortType};
+use crate::processing::functions::{item_id, item_text};
+use crate::processing::html::{char_count, word_count};
+use crate::subscriptions::subscription::Subscription;
 
-/// A collection of items.
+/// A collection of items, each along with the subscription of the feed it came from if known.
+#[derive(Clone)]
 pub struct ItemCollection<'a> {
-    items: Vec<&'a Item>,
+    items: Vec<(&'a Item, Option<&'a Subscription>)>,
+    states: Option<&'a HashMap<String, ItemState>>,
 }
 
 impl<'a> Default for ItemCollection<'a> {
@@ -25,47 +33,65 @@ impl<'a> Default for ItemCollection<'a> {
 impl<'a> ItemCollection<'a> {
     /// Create a new ItemCollection.
     pub fn new() -> ItemCollection<'a> {
-        ItemCollection { items: Vec::new() }
+        ItemCollection {
+            items: Vec::new(),
+            states: None,
+        }
+    }
+
+    /// Create a new ItemCollection which uses the given item states when filtering.
+    /// Items without a state are treated as unread, unstarred and visible.
+    pub fn with_states(states: &'a HashMap<String, ItemState>) -> ItemCollection<'a> {
+        ItemCollection {
+            items: Vec::new(),
+            states: Some(states),
+        }
     }
 
     /// Push a new item to the collection.
     pub fn push(&mut self, item: &'a Item) {
-        self.items.push(item);
+        self.items.push((item, None));
+    }
+
+    /// Push a new item to the collection, along with the subscription of the feed it came from.
+    /// The subscription is used by the folder and tag filters.
+    pub fn push_from(&mut self, item: &'a Item, subscription: &'a Subscription) {
+        self.items.push((item, Some(subscription)));
     }
 
     /// Return a reference to the items in the collection.
     pub fn items(self) -> Vec<&'a Item> {
-        self.items
+        self.items.into_iter().map(|(item, _)| item).collect()
     }
 
     /// Sort the items in the collection.
     /// This alters the actual order of the items stored in the collection.
     pub fn sort(&mut self, sort_type: ItemSortType) {
         match sort_type {
-            ItemSortType::Title => self.items.sort_by(|a, b| a.title().cmp(&b.title())),
-            ItemSortType::Source => self.items.sort_by(|a, b| {
+            ItemSortType::Title => self
+                .items
+                .sort_by(|(a, _), (b, _)| a.title().cmp(&b.title())),
+            ItemSortType::Source => self.items.sort_by(|(a, _), (b, _)| {
                 a.source()
                     .unwrap()
                     .title()
                     .unwrap()
                     .cmp(b.source().unwrap().title().unwrap())
             }),
-            ItemSortType::Date => self.items.sort_by(|a, b| {
+            ItemSortType::Date => self.items.sort_by(|(a, _), (b, _)| {
                 DateTime::parse_from_rfc2822(a.pub_date().unwrap())
                     .unwrap()
                     .cmp(&DateTime::parse_from_rfc2822(b.pub_date().unwrap()).unwrap())
             }),
-            ItemSortType::Length => self.items.sort_by(|a, b| {
-                if let Some(a_description) = a.description() {
-                    if let Some(b_description) = b.description() {
-                        a_description.len().cmp(&b_description.len())
-                    } else {
-                        Ordering::Greater
-                    }
-                } else {
-                    Ordering::Less
-                }
-            }),
+            ItemSortType::Length => self
+                .items
+                .sort_by_cached_key(|(item, _)| item_text(item).map(|text| char_count(&text))),
+            ItemSortType::Words => self
+                .items
+                .sort_by_cached_key(|(item, _)| item_text(item).map(|text| word_count(&text))),
+            ItemSortType::Duration => self
+                .items
+                .sort_by_cached_key(|(item, _)| PodcastEpisode::new(item).duration()),
         };
     }
 
@@ -74,7 +100,7 @@ impl<'a> ItemCollection<'a> {
     pub fn filter(&mut self, filter_type: ItemFilterType) {
         match filter_type {
             ItemFilterType::Title(filter_title) => {
-                self.items.retain(|item| {
+                self.items.retain(|(item, _)| {
                     if let Some(title) = item.title() {
                         title.contains(filter_title.as_str())
                     } else {
@@ -83,7 +109,7 @@ impl<'a> ItemCollection<'a> {
                 });
             }
             ItemFilterType::Source(filter_source) => {
-                self.items.retain(|item| {
+                self.items.retain(|(item, _)| {
                     if let Some(source) = item.source() {
                         if let Some(source_title) = source.title() {
                             source_title.contains(filter_source.as_str())
@@ -96,16 +122,25 @@ impl<'a> ItemCollection<'a> {
                 });
             }
             ItemFilterType::Length(filter_length) => {
-                self.items.retain(|item| {
-                    if let Some(description) = item.description() {
-                        description.len() <= filter_length
+                self.items.retain(|(item, _)| {
+                    if let Some(text) = item_text(item) {
+                        char_count(&text) <= filter_length
+                    } else {
+                        false
+                    }
+                });
+            }
+            ItemFilterType::Words(filter_words) => {
+                self.items.retain(|(item, _)| {
+                    if let Some(text) = item_text(item) {
+                        word_count(&text) <= filter_words
                     } else {
                         false
                     }
                 });
             }
             ItemFilterType::Date(filter_date) => {
-                self.items.retain(|item| {
+                self.items.retain(|(item, _)| {
                     if let Some(date) = item.pub_date() {
                         DateTime::parse_from_rfc2822(date).unwrap()
                             <= DateTime::parse_from_rfc2822(&filter_date).unwrap()
@@ -114,16 +149,86 @@ impl<'a> ItemCollection<'a> {
                     }
                 });
             }
+            ItemFilterType::Unread => {
+                let states = self.states;
+                self.items
+                    .retain(|(item, _)| !state_of(states, item).is_some_and(|state| state.read()));
+            }
+            ItemFilterType::Starred => {
+                let states = self.states;
+                self.items.retain(|(item, _)| {
+                    state_of(states, item).is_some_and(|state| state.starred())
+                });
+            }
+            ItemFilterType::Visible => {
+                let states = self.states;
+                self.items.retain(|(item, _)| {
+                    !state_of(states, item).is_some_and(|state| state.hidden())
+                });
+            }
+            ItemFilterType::Folder(filter_folder) => {
+                self.items.retain(|(_, subscription)| {
+                    if let Some(subscription) = subscription {
+                        subscription.folder().starts_with(&filter_folder)
+                    } else {
+                        false
+                    }
+                });
+            }
+            ItemFilterType::Tagged(filter_tag) => {
+                let states = self.states;
+                self.items.retain(|(item, subscription)| {
+                    let item_tagged =
+                        state_of(states, item).is_some_and(|state| state.has_tag(&filter_tag));
+                    let channel_tagged =
+                        subscription.is_some_and(|subscription| subscription.has_tag(&filter_tag));
+                    item_tagged || channel_tagged
+                });
+            }
+            ItemFilterType::Category(filter_name, filter_domain) => {
+                self.items.retain(|(item, _)| {
+                    Category::of_item(item)
+                        .iter()
+                        .any(|category| category.matches(&filter_name, filter_domain.as_deref()))
+                });
+            }
+            ItemFilterType::HasAudio => {
+                self.items.retain(|(item, _)| {
+                    if let Some(enclosure) = item.enclosure() {
+                        SafeEnclosure::new(enclosure).is_audio()
+                    } else {
+                        false
+                    }
+                });
+            }
         }
     }
 }
 
+/// Return the state of the given item, if any.
+fn state_of<'a>(
+    states: Option<&'a HashMap<String, ItemState>>,
+    item: &Item,
+) -> Option<&'a ItemState> {
+    states.and_then(|states| states.get(&item_id(item)))
+}
+
 #[cfg(test)]
 mod tests {
     use rss::Source;
 
     use super::*;
 
+    /// Return the titles of the items in the given collection, in order.
+    fn titles<'a>(item_collection: &ItemCollection<'a>) -> Vec<&'a str> {
+        item_collection
+            .clone()
+            .items()
+            .into_iter()
+            .filter_map(|item| item.title())
+            .collect()
+    }
+
     #[test]
     fn test_item_collection_push() {
         let item_collection = ItemCollection::new();
@@ -195,6 +300,118 @@ mod tests {
         assert_eq!(items[1].description(), Some("aa"));
         assert_eq!(items[2].description(), Some("aaa"));
     }
+
+    #[test]
+    fn test_item_collection_sort_length_markup() {
+        let mut item_collection = ItemCollection::new();
+
+        // Items, the first is short but heavy on markup and the last has multi-byte characters
+        let mut item = Item::default();
+        item.set_title(String::from("a"));
+        item.set_description(Some(String::from(
+            "<p class=\"intro\"><a href=\"https://example.com/\">Hi</a></p>",
+        )));
+
+        let mut item2 = Item::default();
+        item2.set_title(String::from("b"));
+        item2.set_description(Some(String::from("Four plain words here")));
+
+        let mut item3 = Item::default();
+        item3.set_title(String::from("c"));
+        item3.set_description(Some(String::from("ééééé")));
+
+        item_collection.push(&item2);
+        item_collection.push(&item);
+        item_collection.push(&item3);
+
+        item_collection.sort(ItemSortType::Length);
+        assert_eq!(titles(&item_collection), ["a", "c", "b"]);
+
+        item_collection.sort(ItemSortType::Words);
+        assert_eq!(titles(&item_collection), ["a", "c", "b"]);
+
+        item_collection.filter(ItemFilterType::Length(5));
+        assert_eq!(titles(&item_collection).len(), 2);
+        item_collection.filter(ItemFilterType::Words(0));
+        assert_eq!(titles(&item_collection).len(), 0);
+    }
+
+    #[test]
+    fn test_item_collection_podcast() {
+        let mut item_collection = ItemCollection::new();
+
+        // Items, an hour long episode, a short one, a video and a post without an enclosure
+        let episode = |title: &str, url: &str, mime_type: &str, duration: &str| {
+            let mut item = Item::default();
+            item.set_title(title.to_string());
+            let mut enclosure = rss::Enclosure::default();
+            enclosure.set_url(url);
+            enclosure.set_mime_type(mime_type);
+            item.set_enclosure(enclosure);
+            let mut itunes = rss::extension::itunes::ITunesItemExtension::default();
+            itunes.set_duration(duration.to_string());
+            item.set_itunes_ext(itunes);
+            item
+        };
+        let long = episode(
+            "long",
+            "https://example.com/long.mp3",
+            "audio/mpeg",
+            "1:00:00",
+        );
+        let short = episode("short", "https://example.com/short.m4a", "", "59:59");
+        let video = episode("video", "https://example.com/video.mp4", "video/mp4", "10");
+        let mut post = Item::default();
+        post.set_title(String::from("post"));
+
+        item_collection.push(&long);
+        item_collection.push(&post);
+        item_collection.push(&video);
+        item_collection.push(&short);
+
+        item_collection.sort(ItemSortType::Duration);
+        assert_eq!(titles(&item_collection), ["post", "video", "short", "long"]);
+
+        item_collection.filter(ItemFilterType::HasAudio);
+        assert_eq!(titles(&item_collection), ["short", "long"]);
+    }
+
+    #[test]
+    fn test_item_collection_filter_category() {
+        let mut item_collection = ItemCollection::new();
+
+        // Items
+        let category = |name: &str, domain: Option<&str>| {
+            let mut category = rss::Category::default();
+            category.set_name(name);
+            category.set_domain(domain.map(str::to_string));
+            category
+        };
+        let mut item = Item::default();
+        item.set_title(String::from("a"));
+        item.set_categories(vec![category("Rust", Some("https://example.com/topics"))]);
+
+        let mut item2 = Item::default();
+        item2.set_title(String::from("b"));
+        item2.set_categories(vec![category("rust", None), category("Security", None)]);
+
+        let mut item3 = Item::default();
+        item3.set_title(String::from("c"));
+        item3.set_categories(vec![category("Rustacean", None)]);
+
+        item_collection.push(&item);
+        item_collection.push(&item2);
+        item_collection.push(&item3);
+
+        item_collection.filter(ItemFilterType::Category(String::from("RUST"), None));
+        assert_eq!(titles(&item_collection).len(), 2);
+        item_collection.filter(ItemFilterType::Category(
+            String::from("rust"),
+            Some(String::from("https://example.com/topics")),
+        ));
+        assert_eq!(titles(&item_collection), ["a"]);
+    }
+
     #[test]
     fn test_item_collection_sort_date() {
         let mut item_collection = ItemCollection::new();
@@ -349,4 +566,27 @@ mod tests {
         )));
         assert_eq!(item_collection.items().len(), 2);
     }
+
+    #[test]
+    fn test_item_collection_filter_folder() {
+        // The same item published in two feeds keeps the subscription of each feed
+        let mut item = Item::default();
+        item.set_link(String::from("https://example.com/shared"));
+        let mut news = Subscription::new("https://a.com/feed");
+        news.set_folder(vec![String::from("News")]);
+        news.add_tag("rust");
+        let other = Subscription::new("https://b.com/feed");
+
+        let mut item_collection = ItemCollection::new();
+        item_collection.push_from(&item, &news);
+        item_collection.push_from(&item, &other);
+        item_collection.filter(ItemFilterType::Folder(vec![String::from("News")]));
+        assert_eq!(item_collection.items().len(), 1);
+
+        let mut item_collection = ItemCollection::new();
+        item_collection.push_from(&item, &other);
+        item_collection.push_from(&item, &news);
+        item_collection.filter(ItemFilterType::Tagged(String::from("rust")));
+        assert_eq!(item_collection.items().len(), 1);
+    }
 }
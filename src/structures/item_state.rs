@@ -0,0 +1,116 @@
+//! Definition and implementation of the per-item user state.
+
+// External Imports
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The user state of a single item, keyed by the item's id.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemState {
+    read: bool,
+    starred: bool,
+    hidden: bool,
+    read_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Function implementations for ItemState.
+impl ItemState {
+    /// Create a new unread, unstarred and visible ItemState.
+    pub fn new() -> ItemState {
+        ItemState::default()
+    }
+
+    /// Return whether the item has been read.
+    pub fn read(&self) -> bool {
+        self.read
+    }
+
+    /// Set whether the item has been read.
+    /// Marking an item as unread clears its read timestamp.
+    pub fn set_read(&mut self, read: bool) {
+        self.read = read;
+        if !read {
+            self.read_at = None;
+        }
+    }
+
+    /// Mark the item as read at the given time.
+    pub fn mark_read(&mut self, at: DateTime<Utc>) {
+        self.read = true;
+        self.read_at = Some(at);
+    }
+
+    /// Return when the item was marked as read, if known.
+    pub fn read_at(&self) -> Option<DateTime<Utc>> {
+        self.read_at
+    }
+
+    /// Return whether the item has been starred.
+    pub fn starred(&self) -> bool {
+        self.starred
+    }
+
+    /// Set whether the item has been starred.
+    pub fn set_starred(&mut self, starred: bool) {
+        self.starred = starred;
+    }
+
+    /// Return whether the item has been hidden.
+    pub fn hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Set whether the item has been hidden.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Return the tags of the item.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Tag the item. Tags are compared ignoring case, so adding an existing tag does nothing.
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim();
+        if !tag.is_empty() && !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// Remove the given tag from the item, ignoring case.
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags
+            .retain(|existing| !existing.eq_ignore_ascii_case(tag.trim()));
+    }
+
+    /// Return whether the item is tagged with the given tag, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_item_state_read() {
+        let mut state = ItemState::new();
+        assert!(!state.read());
+        assert_eq!(state.read_at(), None);
+
+        let at = Utc.with_ymd_and_hms(2017, 1, 1, 12, 0, 0).unwrap();
+        state.mark_read(at);
+        assert!(state.read());
+        assert_eq!(state.read_at(), Some(at));
+
+        state.set_read(false);
+        assert!(!state.read());
+        assert_eq!(state.read_at(), None);
+    }
+}
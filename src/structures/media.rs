@@ -0,0 +1,316 @@
+//! Definition and implementation of the thumbnails of items and channels.
+
+// External Imports
+use rss::extension::{Extension, ExtensionMap};
+use rss::{Channel, Item};
+
+// Local Imports
+use super::podcast::attribute;
+use crate::processing::html::{first_image, is_safe_url};
+
+/// The prefix of the Media RSS namespace, `http://search.yahoo.com/mrss/`, whatever prefix the feed declared it with.
+const MEDIA_PREFIX: &str = "media";
+
+/// The prefix of the Atom namespace, `http://www.w3.org/2005/Atom`, when used inside RSS, whatever prefix the feed declared it with.
+const ATOM_PREFIX: &str = "atom";
+
+/// The smallest width or height of an image in a description to be used as a thumbnail, smaller ones are usually icons.
+const MIN_IMAGE_SIZE: u32 = 100;
+
+/// Where a thumbnail was found, from the most to the least preferred.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ThumbnailSource {
+    /// A `<media:thumbnail>`, also inside `<media:content>` or `<media:group>`.
+    MediaThumbnail,
+    /// An image `<media:content>`.
+    MediaContent,
+    /// An `<enclosure>` with an image MIME type.
+    Enclosure,
+    /// An `<atom:link rel="enclosure">` with an image MIME type.
+    AtomLink,
+    /// The first sizable `<img>` in the description or `content:encoded`.
+    Description,
+    /// The channel `<image>` or `<itunes:image>`.
+    Channel,
+}
+
+/// An image representing an item or a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    url: String,
+    width: Option<u32>,
+    height: Option<u32>,
+    source: ThumbnailSource,
+}
+
+/// Function implementations for Thumbnail.
+impl Thumbnail {
+    /// Find the best thumbnail of the given item.
+    /// Media RSS thumbnails are preferred over image media content, image enclosures, Atom enclosure links
+    /// and the first sizable image in the description, in that order. Between images from the same source the largest wins.
+    /// Images with a scheme other than `http`, `https` or `mailto`, e.g. `javascript:` or `data:`, are skipped.
+    pub fn of_item(item: &Item) -> Option<Thumbnail> {
+        let mut candidates = media_thumbnails(item.extensions());
+        if let Some(enclosure) = item.enclosure() {
+            if is_image(Some(enclosure.mime_type())) {
+                candidates.push(Thumbnail::new(
+                    enclosure.url(),
+                    None,
+                    None,
+                    ThumbnailSource::Enclosure,
+                ));
+            }
+        }
+        for link in tags(item.extensions(), ATOM_PREFIX, "link") {
+            if attribute(link, "rel") == Some("enclosure") && is_image(attribute(link, "type")) {
+                if let Some(href) = attribute(link, "href") {
+                    candidates.push(Thumbnail::new(href, None, None, ThumbnailSource::AtomLink));
+                }
+            }
+        }
+        let image = item
+            .description()
+            .and_then(|description| first_image(description, MIN_IMAGE_SIZE))
+            .or_else(|| {
+                item.content()
+                    .and_then(|content| first_image(content, MIN_IMAGE_SIZE))
+            });
+        if let Some((url, width, height)) = image {
+            candidates.push(Thumbnail::new(
+                &url,
+                width,
+                height,
+                ThumbnailSource::Description,
+            ));
+        }
+        candidates
+            .into_iter()
+            .filter(Thumbnail::is_safe)
+            .min_by_key(|thumbnail| (thumbnail.source, std::cmp::Reverse(thumbnail.area())))
+    }
+
+    /// Return the image of the given channel, `<image>` or else `<itunes:image>`, skipping unsafe urls like `of_item`.
+    pub fn of_channel(channel: &Channel) -> Option<Thumbnail> {
+        let dimension = |value: Option<&str>| value.and_then(|value| value.trim().parse().ok());
+        let image = channel.image().map(|image| {
+            Thumbnail::new(
+                image.url(),
+                dimension(image.width()),
+                dimension(image.height()),
+                ThumbnailSource::Channel,
+            )
+        });
+        let itunes = channel
+            .itunes_ext()
+            .and_then(|itunes| itunes.image())
+            .map(|image| Thumbnail::new(image, None, None, ThumbnailSource::Channel));
+        image.into_iter().chain(itunes).find(Thumbnail::is_safe)
+    }
+
+    /// Create a new Thumbnail, trimming its url.
+    fn new(
+        url: &str,
+        width: Option<u32>,
+        height: Option<u32>,
+        source: ThumbnailSource,
+    ) -> Thumbnail {
+        Thumbnail {
+            url: url.trim().to_string(),
+            width,
+            height,
+            source,
+        }
+    }
+
+    /// Return the url of the image.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Return the width of the image in pixels, if published.
+    pub fn width(&self) -> Option<u32> {
+        self.width
+    }
+
+    /// Return the height of the image in pixels, if published.
+    pub fn height(&self) -> Option<u32> {
+        self.height
+    }
+
+    /// Return where the thumbnail was found.
+    pub fn source(&self) -> ThumbnailSource {
+        self.source
+    }
+
+    /// Return whether the url of the image is set and safe to load, see `is_safe_url`.
+    fn is_safe(&self) -> bool {
+        !self.url.is_empty() && is_safe_url(&self.url)
+    }
+
+    /// Return the published area of the image, 0 if its size isn't published.
+    fn area(&self) -> u64 {
+        u64::from(self.width.unwrap_or(0)) * u64::from(self.height.unwrap_or(0))
+    }
+}
+
+/// Return the Media RSS thumbnails and image content among the given extensions, including those nested in groups and content.
+fn media_thumbnails(extensions: &ExtensionMap) -> Vec<Thumbnail> {
+    let mut thumbnails = Vec::new();
+    let mut elements: Vec<&Extension> = tags(extensions, MEDIA_PREFIX, "thumbnail")
+        .chain(tags(extensions, MEDIA_PREFIX, "content"))
+        .chain(tags(extensions, MEDIA_PREFIX, "group"))
+        .collect();
+    while let Some(element) = elements.pop() {
+        let children = element.children();
+        for name in ["thumbnail", "content"] {
+            elements.extend(children.get(name).into_iter().flatten());
+        }
+        let local_name = element.name().rsplit(':').next().unwrap_or_default();
+        let source = match local_name {
+            "thumbnail" => ThumbnailSource::MediaThumbnail,
+            "content"
+                if attribute(element, "medium") == Some("image")
+                    || is_image(attribute(element, "type")) =>
+            {
+                ThumbnailSource::MediaContent
+            }
+            _ => continue,
+        };
+        if let Some(url) = attribute(element, "url") {
+            let dimension = |name| attribute(element, name).and_then(|value| value.parse().ok());
+            thumbnails.push(Thumbnail::new(
+                url,
+                dimension("width"),
+                dimension("height"),
+                source,
+            ));
+        }
+    }
+    thumbnails
+}
+
+/// Return the extension elements with the given prefix and name.
+fn tags<'a>(
+    extensions: &'a ExtensionMap,
+    prefix: &str,
+    name: &str,
+) -> impl Iterator<Item = &'a Extension> {
+    extensions
+        .get(prefix)
+        .and_then(|tags| tags.get(name))
+        .into_iter()
+        .flatten()
+}
+
+/// Return whether the given MIME type is an image type.
+fn is_image(mime_type: Option<&str>) -> bool {
+    mime_type.is_some_and(|mime_type| mime_type.trim().to_ascii_lowercase().starts_with("image/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(elements: &str) -> Item {
+        let feed = format!(
+            "<rss xmlns:media=\"http://search.yahoo.com/mrss/\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\
+            <channel><title>Feed</title><item><title>Item</title>{}</item></channel></rss>",
+            elements
+        );
+        let channel = Channel::read_from(feed.as_bytes()).unwrap();
+        channel.items()[0].clone()
+    }
+
+    #[test]
+    fn test_thumbnail_media_rss() {
+        let item = item(
+            "<media:content url=\"https://example.com/small.jpg\" medium=\"image\" width=\"320\" height=\"240\"/>\
+            <media:group><media:content url=\"https://example.com/video.mp4\" type=\"video/mp4\">\
+            <media:thumbnail url=\"https://example.com/poster.jpg\" width=\"640\" height=\"360\"/></media:content></media:group>\
+            <media:thumbnail url=\"https://example.com/thumb.jpg\" width=\"120\" height=\"90\"/>\
+            <description><![CDATA[<img src=\"https://example.com/inline.jpg\">]]></description>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/poster.jpg");
+        assert_eq!(
+            (thumbnail.width(), thumbnail.height()),
+            (Some(640), Some(360))
+        );
+        assert_eq!(thumbnail.source(), ThumbnailSource::MediaThumbnail);
+
+        let item = self::item(
+            "<media:content url=\"https://example.com/photo.jpg\" type=\"image/jpeg\"/>\
+            <media:content url=\"https://example.com/audio.mp3\" type=\"audio/mpeg\"/>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/photo.jpg");
+        assert_eq!(thumbnail.source(), ThumbnailSource::MediaContent);
+    }
+
+    #[test]
+    fn test_thumbnail_enclosures() {
+        let item = self::item(
+            "<atom:link rel=\"enclosure\" type=\"image/png\" href=\"https://example.com/link.png\"/>\
+            <enclosure url=\"https://example.com/cover.jpg\" length=\"0\" type=\"image/jpeg\"/>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/cover.jpg");
+        assert_eq!(thumbnail.source(), ThumbnailSource::Enclosure);
+
+        let item = self::item(
+            "<atom:link rel=\"alternate\" type=\"image/png\" href=\"https://example.com/page.png\"/>\
+            <atom:link rel=\"enclosure\" type=\"image/png\" href=\"https://example.com/link.png\"/>\
+            <enclosure url=\"https://example.com/episode.mp3\" length=\"0\" type=\"audio/mpeg\"/>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/link.png");
+        assert_eq!(thumbnail.source(), ThumbnailSource::AtomLink);
+    }
+
+    #[test]
+    fn test_thumbnail_unsafe_urls() {
+        let item = self::item(
+            "<media:thumbnail url=\"javascript:alert(1)\"/>\
+            <media:content url=\"java&#9;script:alert(1)\" medium=\"image\"/>\
+            <enclosure url=\"data:image/png;base64,AAAA\" length=\"0\" type=\"image/png\"/>\
+            <atom:link rel=\"enclosure\" type=\"image/png\" href=\"vbscript:run\"/>\
+            <description><![CDATA[<img src=\"https://example.com/photo.jpg\" width=\"800\">]]></description>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/photo.jpg");
+        assert_eq!(thumbnail.source(), ThumbnailSource::Description);
+
+        let mut channel = Channel::default();
+        let mut image = rss::Image::default();
+        image.set_url("javascript:alert(1)");
+        channel.set_image(image);
+        assert_eq!(Thumbnail::of_channel(&channel), None);
+    }
+
+    #[test]
+    fn test_thumbnail_description() {
+        let item = self::item(
+            "<description><![CDATA[<p><img src=\"/icon.png\" width=\"16\"> Text \
+            <img src=\"https://example.com/photo.jpg\" width=\"800\" height=\"600\"></p>]]></description>",
+        );
+        let thumbnail = Thumbnail::of_item(&item).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/photo.jpg");
+        assert_eq!(thumbnail.source(), ThumbnailSource::Description);
+
+        assert_eq!(Thumbnail::of_item(&Item::default()), None);
+    }
+
+    #[test]
+    fn test_thumbnail_channel() {
+        let mut channel = Channel::default();
+        assert_eq!(Thumbnail::of_channel(&channel), None);
+        let mut image = rss::Image::default();
+        image.set_url("https://example.com/logo.png");
+        image.set_width(String::from("144"));
+        channel.set_image(image);
+        let thumbnail = Thumbnail::of_channel(&channel).unwrap();
+        assert_eq!(thumbnail.url(), "https://example.com/logo.png");
+        assert_eq!(thumbnail.width(), Some(144));
+        assert_eq!(thumbnail.source(), ThumbnailSource::Channel);
+    }
+}
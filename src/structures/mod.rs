@@ -1,4 +1,8 @@
 //! Collections module.
+pub mod category;
 pub mod channel_collection;
 pub mod item_collection;
+pub mod item_state;
+pub mod media;
+pub mod podcast;
 pub mod safe_item;
@@ -0,0 +1,525 @@
+//! Definition and implementation of the enclosure and podcast views of items and channels.
+
+// Standard Library Imports
+use std::time::Duration;
+
+// External Imports
+use rss::extension::{Extension, ExtensionMap};
+use rss::{Channel, Enclosure, Item};
+
+/// The prefix of the Podcasting 2.0 namespace, `https://podcastindex.org/namespace/1.0`, whatever prefix the feed declared it with.
+const PODCAST_PREFIX: &str = "podcast";
+
+/// The file extensions of audio enclosures published without a usable MIME type.
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "mp3", "m4a", "m4b", "aac", "ogg", "oga", "opus", "wav", "flac",
+];
+
+/// The file extensions of video enclosures published without a usable MIME type.
+const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "m4v", "mov", "webm", "mkv"];
+
+/// A view of an item's enclosure, with its length parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SafeEnclosure<'a> {
+    url: &'a str,
+    mime_type: Option<&'a str>,
+    length: Option<u64>,
+}
+
+/// Function implementations for SafeEnclosure.
+impl<'a> SafeEnclosure<'a> {
+    /// Create a new SafeEnclosure from the given enclosure.
+    pub fn new(enclosure: &'a Enclosure) -> SafeEnclosure<'a> {
+        SafeEnclosure {
+            url: enclosure.url(),
+            mime_type: Some(enclosure.mime_type().trim()).filter(|mime_type| !mime_type.is_empty()),
+            length: enclosure
+                .length()
+                .trim()
+                .parse()
+                .ok()
+                .filter(|length| *length > 0),
+        }
+    }
+
+    /// Return the url of the media file.
+    pub fn url(&self) -> &'a str {
+        self.url
+    }
+
+    /// Return the MIME type of the media file, if published.
+    pub fn mime_type(&self) -> Option<&'a str> {
+        self.mime_type
+    }
+
+    /// Return the size of the media file in bytes. Feeds often publish `0` when they don't know, which is returned as `None`.
+    pub fn length(&self) -> Option<u64> {
+        self.length
+    }
+
+    /// Return whether the media file is audio, by its MIME type or, without a specific one, its file extension.
+    pub fn is_audio(&self) -> bool {
+        self.is_kind("audio/", &AUDIO_EXTENSIONS)
+    }
+
+    /// Return whether the media file is video, by its MIME type or, without a specific one, its file extension.
+    pub fn is_video(&self) -> bool {
+        self.is_kind("video/", &VIDEO_EXTENSIONS)
+    }
+
+    /// Return whether the MIME type starts with the given prefix or, without a specific one, the url has one of the given extensions.
+    fn is_kind(&self, mime_prefix: &str, extensions: &[&str]) -> bool {
+        match self.mime_type.map(str::to_ascii_lowercase) {
+            Some(mime_type) if mime_type != "application/octet-stream" => {
+                mime_type.starts_with(mime_prefix)
+            }
+            _ => {
+                let path = self.url.split(['?', '#']).next().unwrap_or_default();
+                path.rsplit_once('.').is_some_and(|(_, extension)| {
+                    extensions.contains(&extension.to_ascii_lowercase().as_str())
+                })
+            }
+        }
+    }
+}
+
+/// A Podcasting 2.0 `<podcast:transcript>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transcript<'a> {
+    url: &'a str,
+    mime_type: Option<&'a str>,
+    language: Option<&'a str>,
+    rel: Option<&'a str>,
+}
+
+/// Function implementations for Transcript.
+impl<'a> Transcript<'a> {
+    /// Return the url of the transcript.
+    pub fn url(&self) -> &'a str {
+        self.url
+    }
+
+    /// Return the MIME type of the transcript, e.g. `text/vtt` or `application/srt`.
+    pub fn mime_type(&self) -> Option<&'a str> {
+        self.mime_type
+    }
+
+    /// Return the language of the transcript.
+    pub fn language(&self) -> Option<&'a str> {
+        self.language
+    }
+
+    /// Return the relation of the transcript, `captions` for closed captions.
+    pub fn rel(&self) -> Option<&'a str> {
+        self.rel
+    }
+}
+
+/// A Podcasting 2.0 `<podcast:chapters>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Chapters<'a> {
+    url: &'a str,
+    mime_type: Option<&'a str>,
+}
+
+/// Function implementations for Chapters.
+impl<'a> Chapters<'a> {
+    /// Return the url of the chapters file.
+    pub fn url(&self) -> &'a str {
+        self.url
+    }
+
+    /// Return the MIME type of the chapters file, usually `application/json+chapters`.
+    pub fn mime_type(&self) -> Option<&'a str> {
+        self.mime_type
+    }
+}
+
+/// A Podcasting 2.0 `<podcast:person>`, someone involved in a show or an episode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Person<'a> {
+    name: &'a str,
+    role: Option<&'a str>,
+    group: Option<&'a str>,
+    image: Option<&'a str>,
+    href: Option<&'a str>,
+}
+
+/// Function implementations for Person.
+impl<'a> Person<'a> {
+    /// Return the name of the person.
+    pub fn name(&self) -> &'a str {
+        self.name
+    }
+
+    /// Return the role of the person, `host` when not published.
+    pub fn role(&self) -> &'a str {
+        self.role.unwrap_or("host")
+    }
+
+    /// Return the group of the role, `cast` when not published.
+    pub fn group(&self) -> &'a str {
+        self.group.unwrap_or("cast")
+    }
+
+    /// Return the url of a picture of the person.
+    pub fn image(&self) -> Option<&'a str> {
+        self.image
+    }
+
+    /// Return the url of a page about the person.
+    pub fn href(&self) -> Option<&'a str> {
+        self.href
+    }
+}
+
+/// A Podcasting 2.0 `<podcast:funding>`, a way to support a show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Funding<'a> {
+    url: &'a str,
+    text: Option<&'a str>,
+}
+
+/// Function implementations for Funding.
+impl<'a> Funding<'a> {
+    /// Return the url of the donation or membership page.
+    pub fn url(&self) -> &'a str {
+        self.url
+    }
+
+    /// Return the call to action shown with the link.
+    pub fn text(&self) -> Option<&'a str> {
+        self.text
+    }
+}
+
+/// A view of the podcast metadata of an item, from the iTunes and Podcasting 2.0 namespaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodcastEpisode<'a> {
+    duration: Option<Duration>,
+    episode: Option<u32>,
+    season: Option<u32>,
+    explicit: Option<bool>,
+    image: Option<&'a str>,
+    transcripts: Vec<Transcript<'a>>,
+    chapters: Option<Chapters<'a>>,
+    persons: Vec<Person<'a>>,
+    funding: Vec<Funding<'a>>,
+}
+
+/// Function implementations for PodcastEpisode.
+impl<'a> PodcastEpisode<'a> {
+    /// Create a new PodcastEpisode from the given item.
+    pub fn new(item: &'a Item) -> PodcastEpisode<'a> {
+        let itunes = item.itunes_ext();
+        let extensions = item.extensions();
+        PodcastEpisode {
+            duration: itunes
+                .and_then(|itunes| itunes.duration())
+                .and_then(parse_duration),
+            episode: itunes
+                .and_then(|itunes| itunes.episode())
+                .and_then(|episode| episode.trim().parse().ok()),
+            season: itunes
+                .and_then(|itunes| itunes.season())
+                .and_then(|season| season.trim().parse().ok()),
+            explicit: itunes
+                .and_then(|itunes| itunes.explicit())
+                .and_then(parse_explicit),
+            image: itunes.and_then(|itunes| itunes.image()),
+            transcripts: podcast_tags(extensions, "transcript")
+                .filter_map(|tag| {
+                    Some(Transcript {
+                        url: attribute(tag, "url")?,
+                        mime_type: attribute(tag, "type"),
+                        language: attribute(tag, "language"),
+                        rel: attribute(tag, "rel"),
+                    })
+                })
+                .collect(),
+            chapters: podcast_tags(extensions, "chapters").find_map(|tag| {
+                Some(Chapters {
+                    url: attribute(tag, "url")?,
+                    mime_type: attribute(tag, "type"),
+                })
+            }),
+            persons: persons(extensions),
+            funding: funding(extensions),
+        }
+    }
+
+    /// Return the duration of the episode, from `<itunes:duration>` in seconds, `MM:SS` or `HH:MM:SS`.
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration
+    }
+
+    /// Return the episode number.
+    pub fn episode(&self) -> Option<u32> {
+        self.episode
+    }
+
+    /// Return the season number.
+    pub fn season(&self) -> Option<u32> {
+        self.season
+    }
+
+    /// Return whether the episode is marked as explicit, `None` when it isn't marked either way.
+    pub fn explicit(&self) -> Option<bool> {
+        self.explicit
+    }
+
+    /// Return the url of the episode artwork.
+    pub fn image(&self) -> Option<&'a str> {
+        self.image
+    }
+
+    /// Return the transcripts of the episode.
+    pub fn transcripts(&self) -> &[Transcript<'a>] {
+        &self.transcripts
+    }
+
+    /// Return the chapters of the episode.
+    pub fn chapters(&self) -> Option<Chapters<'a>> {
+        self.chapters
+    }
+
+    /// Return the people involved in the episode.
+    pub fn persons(&self) -> &[Person<'a>] {
+        &self.persons
+    }
+
+    /// Return the ways to support the episode.
+    pub fn funding(&self) -> &[Funding<'a>] {
+        &self.funding
+    }
+}
+
+/// A view of the podcast metadata of a channel, from the iTunes and Podcasting 2.0 namespaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PodcastShow<'a> {
+    author: Option<&'a str>,
+    explicit: Option<bool>,
+    image: Option<&'a str>,
+    persons: Vec<Person<'a>>,
+    funding: Vec<Funding<'a>>,
+}
+
+/// Function implementations for PodcastShow.
+impl<'a> PodcastShow<'a> {
+    /// Create a new PodcastShow from the given channel.
+    pub fn new(channel: &'a Channel) -> PodcastShow<'a> {
+        let itunes = channel.itunes_ext();
+        PodcastShow {
+            author: itunes.and_then(|itunes| itunes.author()),
+            explicit: itunes
+                .and_then(|itunes| itunes.explicit())
+                .and_then(parse_explicit),
+            image: itunes
+                .and_then(|itunes| itunes.image())
+                .or_else(|| channel.image().map(|image| image.url())),
+            persons: persons(channel.extensions()),
+            funding: funding(channel.extensions()),
+        }
+    }
+
+    /// Return the author of the show.
+    pub fn author(&self) -> Option<&'a str> {
+        self.author
+    }
+
+    /// Return whether the show is marked as explicit, `None` when it isn't marked either way.
+    pub fn explicit(&self) -> Option<bool> {
+        self.explicit
+    }
+
+    /// Return the url of the show artwork, `<itunes:image>` or else the channel image.
+    pub fn image(&self) -> Option<&'a str> {
+        self.image
+    }
+
+    /// Return the people involved in the show.
+    pub fn persons(&self) -> &[Person<'a>] {
+        &self.persons
+    }
+
+    /// Return the ways to support the show.
+    pub fn funding(&self) -> &[Funding<'a>] {
+        &self.funding
+    }
+}
+
+/// Parse an `<itunes:duration>`, which is either a number of seconds or `MM:SS` or `HH:MM:SS`.
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let parts: Vec<&str> = duration.trim().split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let (seconds, minutes_and_hours) = parts.split_last()?;
+    let mut total: u64 = 0;
+    for part in minutes_and_hours {
+        total = total
+            .checked_mul(60)?
+            .checked_add(part.trim().parse::<u64>().ok()?)?;
+    }
+    let seconds = Duration::try_from_secs_f64(seconds.trim().parse::<f64>().ok()?).ok()?;
+    Duration::from_secs(total.checked_mul(60)?).checked_add(seconds)
+}
+
+/// Parse an `<itunes:explicit>`, which is `true`, `yes` or `explicit`, or `false`, `no` or `clean`.
+fn parse_explicit(explicit: &str) -> Option<bool> {
+    match explicit.trim().to_ascii_lowercase().as_str() {
+        "true" | "yes" | "explicit" => Some(true),
+        "false" | "no" | "clean" => Some(false),
+        _ => None,
+    }
+}
+
+/// Return the Podcasting 2.0 tags with the given name.
+fn podcast_tags<'a>(
+    extensions: &'a ExtensionMap,
+    name: &'a str,
+) -> impl Iterator<Item = &'a Extension> + 'a {
+    extensions
+        .get(PODCAST_PREFIX)
+        .and_then(|tags| tags.get(name))
+        .into_iter()
+        .flatten()
+}
+
+/// Return the given attribute of the given tag, if it isn't blank.
+pub(crate) fn attribute<'a>(tag: &'a Extension, name: &str) -> Option<&'a str> {
+    tag.attrs()
+        .get(name)
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+}
+
+/// Return the text of the given tag, if it isn't blank.
+fn text(tag: &Extension) -> Option<&str> {
+    tag.value().map(str::trim).filter(|value| !value.is_empty())
+}
+
+/// Return the `<podcast:person>` tags with a name.
+fn persons(extensions: &ExtensionMap) -> Vec<Person<'_>> {
+    podcast_tags(extensions, "person")
+        .filter_map(|tag| {
+            Some(Person {
+                name: text(tag)?,
+                role: attribute(tag, "role"),
+                group: attribute(tag, "group"),
+                image: attribute(tag, "img"),
+                href: attribute(tag, "href"),
+            })
+        })
+        .collect()
+}
+
+/// Return the `<podcast:funding>` tags with a url.
+fn funding(extensions: &ExtensionMap) -> Vec<Funding<'_>> {
+    podcast_tags(extensions, "funding")
+        .filter_map(|tag| {
+            Some(Funding {
+                url: attribute(tag, "url")?,
+                text: text(tag),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEED: &str = r#"<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd" xmlns:podcast="https://podcastindex.org/namespace/1.0">
+<channel>
+  <title>Show</title>
+  <itunes:author>Jane Host</itunes:author>
+  <itunes:explicit>no</itunes:explicit>
+  <itunes:image href="https://example.com/show.jpg"/>
+  <podcast:funding url="https://example.com/support">Support the show</podcast:funding>
+  <item>
+    <title>Episode 3</title>
+    <enclosure url="https://example.com/3.mp3?source=feed" length="0" type="application/octet-stream"/>
+    <itunes:duration>1:02:03</itunes:duration>
+    <itunes:episode>3</itunes:episode>
+    <itunes:season>2</itunes:season>
+    <itunes:explicit>true</itunes:explicit>
+    <podcast:transcript url="https://example.com/3.vtt" type="text/vtt" language="en" rel="captions"/>
+    <podcast:chapters url="https://example.com/3.json" type="application/json+chapters"/>
+    <podcast:person role="guest" href="https://example.com/sam">Sam Guest</podcast:person>
+    <podcast:person>Jane Host</podcast:person>
+  </item>
+  <item>
+    <title>Trailer</title>
+    <enclosure url="https://example.com/trailer" length="1234" type="video/mp4"/>
+    <itunes:duration>95</itunes:duration>
+  </item>
+</channel>
+</rss>"#;
+
+    #[test]
+    fn test_podcast_show() {
+        let channel = Channel::read_from(FEED.as_bytes()).unwrap();
+        let show = PodcastShow::new(&channel);
+        assert_eq!(show.author(), Some("Jane Host"));
+        assert_eq!(show.explicit(), Some(false));
+        assert_eq!(show.image(), Some("https://example.com/show.jpg"));
+        assert_eq!(show.funding()[0].url(), "https://example.com/support");
+        assert_eq!(show.funding()[0].text(), Some("Support the show"));
+        assert!(show.persons().is_empty());
+    }
+
+    #[test]
+    fn test_podcast_episode() {
+        let channel = Channel::read_from(FEED.as_bytes()).unwrap();
+        let episode = PodcastEpisode::new(&channel.items()[0]);
+        assert_eq!(episode.duration(), Some(Duration::from_secs(3723)));
+        assert_eq!((episode.season(), episode.episode()), (Some(2), Some(3)));
+        assert_eq!(episode.explicit(), Some(true));
+        assert_eq!(episode.transcripts()[0].url(), "https://example.com/3.vtt");
+        assert_eq!(episode.transcripts()[0].rel(), Some("captions"));
+        assert_eq!(
+            episode.chapters().map(|chapters| chapters.url()),
+            Some("https://example.com/3.json")
+        );
+        let persons: Vec<_> = episode
+            .persons()
+            .iter()
+            .map(|person| (person.name(), person.role()))
+            .collect();
+        assert_eq!(persons, [("Sam Guest", "guest"), ("Jane Host", "host")]);
+
+        let trailer = PodcastEpisode::new(&channel.items()[1]);
+        assert_eq!(trailer.duration(), Some(Duration::from_secs(95)));
+        assert_eq!(trailer.episode(), None);
+    }
+
+    #[test]
+    fn test_safe_enclosure() {
+        let channel = Channel::read_from(FEED.as_bytes()).unwrap();
+        let audio = SafeEnclosure::new(channel.items()[0].enclosure().unwrap());
+        assert_eq!(audio.length(), None);
+        assert!(audio.is_audio());
+        assert!(!audio.is_video());
+        let video = SafeEnclosure::new(channel.items()[1].enclosure().unwrap());
+        assert_eq!(video.length(), Some(1234));
+        assert_eq!(video.mime_type(), Some("video/mp4"));
+        assert!(video.is_video());
+        assert!(!video.is_audio());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("45:30"), Some(Duration::from_secs(2730)));
+        assert_eq!(parse_duration(" 61.5 "), Some(Duration::from_millis(61500)));
+        assert_eq!(parse_duration("1:2:3:4"), None);
+        assert_eq!(parse_duration("soon"), None);
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("-1"), None);
+        assert_eq!(parse_duration("NaN"), None);
+        // Durations too long to represent are rejected instead of overflowing
+        assert_eq!(parse_duration("1e300"), None);
+        assert_eq!(parse_duration("18446744073709551615:0"), None);
+        assert_eq!(parse_duration("307445734561825861:0:0"), None);
+    }
+}
@@ -1,15 +1,25 @@
-use rss::Item;
+use rss::{Channel, Item};
 
+use super::media::Thumbnail;
+use super::podcast::{PodcastEpisode, SafeEnclosure};
+use crate::processing::html::{html_to_text, sanitize_html, summarize};
+
+/// A view of an item with defaults for missing fields and its HTML sanitized with `sanitize_html`.
 pub struct SafeItem<'a> {
     title: &'a str,
     link: &'a str,
-    description: &'a str,
+    description: String,
+    raw_description: &'a str,
+    content: Option<String>,
     pub_date: &'a str,
     author: &'a str,
+    enclosure: Option<SafeEnclosure<'a>>,
+    podcast: PodcastEpisode<'a>,
+    thumbnail: Option<Thumbnail>,
 }
 
 impl<'a> SafeItem<'a> {
-    pub fn new(item: &Item) -> SafeItem {
+    pub fn new(item: &Item) -> SafeItem<'_> {
         let mut title = "No title";
         if let Some(title_some) = item.title() {
             title = title_some;
@@ -38,10 +48,23 @@ impl<'a> SafeItem<'a> {
         SafeItem {
             title,
             link,
-            description,
+            description: sanitize_html(description),
+            raw_description: description,
+            content: item.content().map(sanitize_html),
             pub_date,
             author,
+            enclosure: item.enclosure().map(SafeEnclosure::new),
+            podcast: PodcastEpisode::new(item),
+            thumbnail: Thumbnail::of_item(item),
+        }
+    }
+
+    /// Use the image of the given channel, the item's channel, as the thumbnail of an item without one.
+    pub fn with_channel_image(mut self, channel: &Channel) -> SafeItem<'a> {
+        if self.thumbnail.is_none() {
+            self.thumbnail = Thumbnail::of_channel(channel);
         }
+        self
     }
 }
 
@@ -55,8 +78,29 @@ impl<'a> SafeItem<'a> {
         self.link
     }
 
-    pub fn description(&self) -> &'a str {
-        self.description
+    /// Return the sanitized description, safe to embed in a page.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Return the description as published, which may contain scripts and other unsafe markup.
+    pub fn raw_description(&self) -> &'a str {
+        self.raw_description
+    }
+
+    /// Return the sanitized `content:encoded`, safe to embed in a page.
+    pub fn content(&self) -> Option<&str> {
+        self.content.as_deref()
+    }
+
+    /// Return the plain text of the description, without any markup.
+    pub fn text(&self) -> String {
+        html_to_text(self.raw_description)
+    }
+
+    /// Return the plain text of the description shortened to at most `max_chars` characters.
+    pub fn summary(&self, max_chars: usize) -> String {
+        summarize(&self.text(), max_chars)
     }
 
     pub fn pub_date(&self) -> &'a str {
@@ -66,4 +110,65 @@ impl<'a> SafeItem<'a> {
     pub fn author(&self) -> &'a str {
         self.author
     }
+
+    /// Return the enclosure of the item, e.g. the audio file of a podcast episode.
+    pub fn enclosure(&self) -> Option<&SafeEnclosure<'a>> {
+        self.enclosure.as_ref()
+    }
+
+    /// Return the podcast metadata of the item.
+    pub fn podcast(&self) -> &PodcastEpisode<'a> {
+        &self.podcast
+    }
+
+    /// Return the image representing the item, see `Thumbnail::of_item`.
+    pub fn thumbnail(&self) -> Option<&Thumbnail> {
+        self.thumbnail.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_item() {
+        let mut item = Item::default();
+        item.set_description("<p onmouseover=\"x()\">Hi<script>x()</script></p>".to_string());
+        item.set_content("<a href=\"/more\">More</a>".to_string());
+        let safe_item = SafeItem::new(&item);
+        assert_eq!(safe_item.title(), "No title");
+        assert_eq!(safe_item.description(), "<p>Hi</p>");
+        assert!(safe_item.raw_description().contains("<script>"));
+        assert_eq!(safe_item.text(), "Hi");
+        assert_eq!(safe_item.summary(1), "…");
+        assert!(safe_item.enclosure().is_none());
+        assert_eq!(safe_item.podcast().duration(), None);
+        assert_eq!(
+            safe_item.content(),
+            Some("<a href=\"/more\" rel=\"noopener noreferrer\">More</a>")
+        );
+    }
+
+    #[test]
+    fn test_safe_item_thumbnail() {
+        let mut channel = Channel::default();
+        let mut image = rss::Image::default();
+        image.set_url("https://example.com/logo.png");
+        channel.set_image(image);
+
+        let mut item = Item::default();
+        let safe_item = SafeItem::new(&item).with_channel_image(&channel);
+        assert_eq!(
+            safe_item.thumbnail().map(Thumbnail::url),
+            Some("https://example.com/logo.png")
+        );
+
+        item.set_description(String::from("<img src=\"https://example.com/photo.jpg\">"));
+        let safe_item = SafeItem::new(&item).with_channel_image(&channel);
+        assert_eq!(
+            safe_item.thumbnail().map(Thumbnail::url),
+            Some("https://example.com/photo.jpg")
+        );
+    }
 }
@@ -0,0 +1,118 @@
+//! Definition and implementation of the subscription folder tree.
+
+// Local Imports
+use super::subscription::Subscription;
+
+/// A folder of subscriptions along with its sub-folders.
+/// The root folder has an empty name and path.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Folder {
+    name: String,
+    path: Vec<String>,
+    urls: Vec<String>,
+    folders: Vec<Folder>,
+}
+
+/// Function implementations for Folder.
+impl Folder {
+    /// Create a new empty folder at the given path.
+    pub fn new(path: Vec<String>) -> Folder {
+        Folder {
+            name: path.last().cloned().unwrap_or_default(),
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// Build the folder tree holding the given subscriptions.
+    /// The given folder paths are included even when they hold no subscriptions.
+    pub fn tree(folders: &[Vec<String>], subscriptions: &[&Subscription]) -> Folder {
+        let mut root = Folder::new(vec![]);
+        for path in folders {
+            root.folder_mut(path);
+        }
+        for subscription in subscriptions {
+            root.folder_mut(subscription.folder())
+                .urls
+                .push(subscription.url().to_string());
+        }
+        root
+    }
+
+    /// Return the name of the folder.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the path of folder names leading to this folder, outermost first.
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    /// Return the feed urls of the subscriptions filed directly in this folder.
+    pub fn urls(&self) -> &[String] {
+        &self.urls
+    }
+
+    /// Return the sub-folders of this folder.
+    pub fn folders(&self) -> &[Folder] {
+        &self.folders
+    }
+
+    /// Return the feed urls of the subscriptions in this folder and all of its sub-folders.
+    pub fn all_urls(&self) -> Vec<&str> {
+        let mut urls: Vec<&str> = self.urls.iter().map(String::as_str).collect();
+        for folder in &self.folders {
+            urls.extend(folder.all_urls());
+        }
+        urls
+    }
+
+    /// Return the folder at the given path relative to this one, creating it and its parents if needed.
+    fn folder_mut(&mut self, path: &[String]) -> &mut Folder {
+        match path.first() {
+            None => self,
+            Some(name) => {
+                let index = match self.folders.iter().position(|f| &f.name == name) {
+                    Some(index) => index,
+                    None => {
+                        let mut child_path = self.path.clone();
+                        child_path.push(name.clone());
+                        self.folders.push(Folder::new(child_path));
+                        self.folders.len() - 1
+                    }
+                };
+                self.folders[index].folder_mut(&path[1..])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_folder_tree() {
+        let mut a = Subscription::new("https://a.com/feed");
+        a.set_folder(vec![String::from("News"), String::from("Local")]);
+        let mut b = Subscription::new("https://b.com/feed");
+        b.set_folder(vec![String::from("News")]);
+        let c = Subscription::new("https://c.com/feed");
+
+        let folders = [vec![String::from("Empty")]];
+        let root = Folder::tree(&folders, &[&a, &b, &c]);
+        assert_eq!(root.name(), "");
+        assert_eq!(root.urls(), ["https://c.com/feed"]);
+        assert_eq!(root.folders().len(), 2);
+        assert_eq!(root.folders()[0].name(), "Empty");
+
+        let news = &root.folders()[1];
+        assert_eq!(news.urls(), ["https://b.com/feed"]);
+        assert_eq!(news.folders()[0].path(), ["News", "Local"]);
+        assert_eq!(
+            news.all_urls(),
+            vec!["https://b.com/feed", "https://a.com/feed"]
+        );
+    }
+}
@@ -0,0 +1,4 @@
+//! Subscriptions module.
+pub mod folder;
+pub mod opml;
+pub mod subscription;
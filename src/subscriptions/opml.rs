@@ -0,0 +1,312 @@
+//! Set of functions for importing and exporting OPML subscription lists.
+
+// std imports
+use std::error::Error;
+
+// third-party imports
+use quick_xml::{
+    encoding::Decoder,
+    escape::escape,
+    events::{BytesStart, Event},
+    Reader, XmlVersion,
+};
+
+// Local Imports
+use super::subscription::Subscription;
+
+/// Parse the subscriptions from the given OPML document.
+/// Outlines without an `xmlUrl` are treated as folders, and are recorded as the folder of the outlines nested within.
+pub fn parse_opml(contents: &str) -> Result<Vec<Subscription>, Box<dyn Error>> {
+    read_opml(contents, &mut Vec::new())
+}
+
+/// Parse the paths of every folder in the given OPML document, outermost first, including the ones holding no subscriptions.
+/// Folders are returned in the order they appear in.
+pub fn parse_opml_folders(contents: &str) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+    let mut folders = Vec::new();
+    read_opml(contents, &mut folders)?;
+    Ok(folders)
+}
+
+/// Write the given subscriptions as an OPML 2.0 document, nesting them in outlines per folder.
+/// Folders are written in the order they first appear in.
+pub fn write_opml(title: &str, subscriptions: &[Subscription]) -> String {
+    write_opml_with_folders(title, &[], subscriptions)
+}
+
+/// Write the given subscriptions as an OPML 2.0 document like `write_opml`,
+/// also writing the given folder paths when they hold no subscriptions.
+pub fn write_opml_with_folders(
+    title: &str,
+    folders: &[Vec<String>],
+    subscriptions: &[Subscription],
+) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n");
+    out.push_str(&format!("    <title>{}</title>\n", escape(title)));
+    out.push_str("  </head>\n");
+    out.push_str("  <body>\n");
+    let subscriptions: Vec<&Subscription> = subscriptions.iter().collect();
+    let folders: Vec<&[String]> = folders.iter().map(Vec::as_slice).collect();
+    write_outlines(&mut out, &subscriptions, &folders, 0);
+    out.push_str("  </body>\n");
+    out.push_str("</opml>\n");
+    out
+}
+
+/// Parse the subscriptions from the given OPML document, adding the folder paths it contains to the given folders.
+fn read_opml(
+    contents: &str,
+    folders: &mut Vec<Vec<String>>,
+) -> Result<Vec<Subscription>, Box<dyn Error>> {
+    let mut reader = Reader::from_str(contents);
+    let mut subscriptions = Vec::new();
+    // One entry per open outline, holding the folder name if the outline is a folder
+    let mut outlines: Vec<Option<String>> = Vec::new();
+    let mut found_opml = false;
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) if e.local_name().as_ref() == b"opml" => found_opml = true,
+            Event::Start(e) if e.local_name().as_ref() == b"outline" => {
+                let folder = folder_path(&outlines);
+                match parse_outline(&e, reader.decoder(), folder)? {
+                    Some(subscription) => {
+                        subscriptions.push(subscription);
+                        outlines.push(None);
+                    }
+                    None => {
+                        outlines.push(Some(outline_title(&e, reader.decoder())?));
+                        add_folder(folders, folder_path(&outlines));
+                    }
+                }
+            }
+            Event::Empty(e) if e.local_name().as_ref() == b"outline" => {
+                let mut folder = folder_path(&outlines);
+                match parse_outline(&e, reader.decoder(), folder.clone())? {
+                    Some(subscription) => subscriptions.push(subscription),
+                    None => {
+                        folder.push(outline_title(&e, reader.decoder())?);
+                        add_folder(folders, folder);
+                    }
+                }
+            }
+            Event::End(e) if e.local_name().as_ref() == b"outline" => {
+                outlines.pop();
+            }
+            Event::Eof => break,
+            _ => (),
+        }
+    }
+    if !found_opml {
+        return Err("document is not an OPML document".into());
+    }
+    Ok(subscriptions)
+}
+
+/// Add the given folder path to the folders, unless it is already there.
+fn add_folder(folders: &mut Vec<Vec<String>>, path: Vec<String>) {
+    if !folders.contains(&path) {
+        folders.push(path);
+    }
+}
+
+/// Return the folder path described by the currently open outlines.
+fn folder_path(outlines: &[Option<String>]) -> Vec<String> {
+    outlines.iter().flatten().cloned().collect()
+}
+
+/// Return the value of the given attribute, if present.
+fn attribute(
+    e: &BytesStart,
+    decoder: Decoder,
+    name: &str,
+) -> Result<Option<String>, Box<dyn Error>> {
+    match e.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(
+            attr.decoded_and_normalized_value(XmlVersion::Implicit1_0, decoder)?
+                .into_owned(),
+        )),
+        None => Ok(None),
+    }
+}
+
+/// Return the title of an outline, preferring `title` over the required `text` attribute.
+fn outline_title(e: &BytesStart, decoder: Decoder) -> Result<String, Box<dyn Error>> {
+    let title = match attribute(e, decoder, "title")? {
+        Some(title) => Some(title),
+        None => attribute(e, decoder, "text")?,
+    };
+    Ok(title.unwrap_or_default())
+}
+
+/// Parse an outline into a subscription. Returns None if the outline has no `xmlUrl`.
+fn parse_outline(
+    e: &BytesStart,
+    decoder: Decoder,
+    folder: Vec<String>,
+) -> Result<Option<Subscription>, Box<dyn Error>> {
+    let url = match attribute(e, decoder, "xmlUrl")? {
+        Some(url) if !url.trim().is_empty() => url,
+        _ => return Ok(None),
+    };
+    let mut subscription = Subscription::new(url.trim());
+    subscription.set_title(&outline_title(e, decoder)?);
+    subscription.set_html_url(attribute(e, decoder, "htmlUrl")?);
+    if let Some(category) = attribute(e, decoder, "category")? {
+        let categories = category
+            .split(',')
+            .map(str::trim)
+            .filter(|category| !category.is_empty())
+            .map(String::from)
+            .collect();
+        subscription.set_categories(categories);
+    }
+    subscription.set_folder(folder);
+    Ok(Some(subscription))
+}
+
+/// Write the subscriptions and folders filed at the given folder depth, recursing into their sub-folders.
+/// Folders holding no subscriptions are written after the others.
+fn write_outlines(
+    out: &mut String,
+    subscriptions: &[&Subscription],
+    folders: &[&[String]],
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth + 2);
+    let mut written_folders: Vec<&str> = Vec::new();
+    for subscription in subscriptions {
+        match subscription.folder().get(depth) {
+            None => {
+                out.push_str(&format!(
+                    "{}<outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"",
+                    indent,
+                    escape(subscription.title()),
+                    escape(subscription.title()),
+                    escape(subscription.url())
+                ));
+                if let Some(html_url) = subscription.html_url() {
+                    out.push_str(&format!(" htmlUrl=\"{}\"", escape(html_url)));
+                }
+                if !subscription.categories().is_empty() {
+                    let categories = subscription.categories().join(",");
+                    out.push_str(&format!(" category=\"{}\"", escape(categories.as_str())));
+                }
+                out.push_str("/>\n");
+            }
+            Some(folder) if !written_folders.contains(&folder.as_str()) => {
+                written_folders.push(folder);
+                write_folder(out, folder, subscriptions, folders, depth);
+            }
+            Some(_) => (),
+        }
+    }
+    for path in folders {
+        match path.get(depth) {
+            Some(folder) if !written_folders.contains(&folder.as_str()) => {
+                written_folders.push(folder);
+                write_folder(out, folder, subscriptions, folders, depth);
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Write the folder with the given name at the given depth, along with the subscriptions and folders filed in it.
+fn write_folder(
+    out: &mut String,
+    folder: &str,
+    subscriptions: &[&Subscription],
+    folders: &[&[String]],
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth + 2);
+    let children: Vec<&Subscription> = subscriptions
+        .iter()
+        .filter(|child| child.folder().get(depth).map(String::as_str) == Some(folder))
+        .copied()
+        .collect();
+    let child_folders: Vec<&[String]> = folders
+        .iter()
+        .filter(|path| path.get(depth).map(String::as_str) == Some(folder))
+        .copied()
+        .collect();
+    out.push_str(&format!(
+        "{}<outline text=\"{}\" title=\"{}\">\n",
+        indent,
+        escape(folder),
+        escape(folder)
+    ));
+    write_outlines(out, &children, &child_folders, depth + 1);
+    out.push_str(&format!("{}</outline>\n", indent));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    /// Test wether parse_opml() reads nested folders and the outline attributes
+    fn test_parse_opml() {
+        let f = fs::read_to_string("./resources/testing/example.opml").unwrap();
+        let subscriptions = parse_opml(&f).unwrap();
+        assert_eq!(subscriptions.len(), 4);
+
+        assert_eq!(
+            subscriptions[0].url(),
+            "https://blog.rust-lang.org/feed.xml"
+        );
+        assert_eq!(subscriptions[0].title(), "Rust Blog");
+        assert_eq!(
+            subscriptions[0].html_url(),
+            Some("https://blog.rust-lang.org/")
+        );
+        assert_eq!(subscriptions[0].categories(), ["rust", "programming"]);
+        assert_eq!(subscriptions[0].folder(), ["Programming"]);
+
+        assert_eq!(subscriptions[1].folder(), ["Programming", "Releases"]);
+        assert_eq!(subscriptions[1].title(), "Rust releases & notes");
+        assert_eq!(subscriptions[2].folder(), ["Programming"]);
+        assert!(subscriptions[3].folder().is_empty());
+
+        let folders = parse_opml_folders(&f).unwrap();
+        assert_eq!(
+            folders,
+            [
+                vec!["Programming"],
+                vec!["Programming", "Releases"],
+                vec!["Empty folder"]
+            ]
+        );
+    }
+
+    #[test]
+    /// Test wether parse_opml() rejects documents that aren't OPML
+    fn test_parse_opml_invalid() {
+        let f = fs::read_to_string("./resources/testing/example.rss").unwrap();
+        assert!(parse_opml(&f).is_err());
+    }
+
+    #[test]
+    /// Test wether write_opml() output parses back into the same subscriptions
+    fn test_write_opml_roundtrip() {
+        let f = fs::read_to_string("./resources/testing/example.opml").unwrap();
+        let subscriptions = parse_opml(&f).unwrap();
+        let written = write_opml("Subscriptions", &subscriptions);
+        let reparsed = parse_opml(&written).unwrap();
+        // Folders are grouped, so the subscriptions in "Programming" come first
+        assert_eq!(reparsed.len(), subscriptions.len());
+        for subscription in &subscriptions {
+            assert!(reparsed.contains(subscription));
+        }
+
+        // Folders holding no subscriptions are only kept when they are passed along
+        assert_eq!(parse_opml_folders(&written).unwrap().len(), 2);
+        let folders = parse_opml_folders(&f).unwrap();
+        let written = write_opml_with_folders("Subscriptions", &folders, &subscriptions);
+        assert_eq!(parse_opml_folders(&written).unwrap(), folders);
+        assert_eq!(parse_opml(&written).unwrap(), reparsed);
+    }
+}
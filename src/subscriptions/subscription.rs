@@ -0,0 +1,134 @@
+//! Definition and implementation of a feed subscription.
+
+// External Imports
+use serde::{Deserialize, Serialize};
+
+/// A subscription to a single feed, along with the folder it is filed under and the tags it has been given.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Subscription {
+    url: String,
+    title: String,
+    html_url: Option<String>,
+    categories: Vec<String>,
+    folder: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    dead: bool,
+}
+
+/// Function implementations for Subscription.
+impl Subscription {
+    /// Create a new subscription to the feed at the given url, filed under the root folder.
+    pub fn new(url: &str) -> Subscription {
+        Subscription {
+            url: url.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Return the url of the feed.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Set the url of the feed.
+    pub fn set_url(&mut self, url: &str) {
+        self.url = url.to_string();
+    }
+
+    /// Return the title of the subscription.
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    /// Set the title of the subscription.
+    pub fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    /// Return the url of the website the feed belongs to.
+    pub fn html_url(&self) -> Option<&str> {
+        self.html_url.as_deref()
+    }
+
+    /// Set the url of the website the feed belongs to.
+    pub fn set_html_url(&mut self, html_url: Option<String>) {
+        self.html_url = html_url;
+    }
+
+    /// Return the categories of the subscription.
+    pub fn categories(&self) -> &[String] {
+        &self.categories
+    }
+
+    /// Set the categories of the subscription.
+    pub fn set_categories(&mut self, categories: Vec<String>) {
+        self.categories = categories;
+    }
+
+    /// Return the path of folder names the subscription is filed under, outermost first.
+    /// An empty path is the root folder.
+    pub fn folder(&self) -> &[String] {
+        &self.folder
+    }
+
+    /// Set the path of folder names the subscription is filed under, outermost first.
+    pub fn set_folder(&mut self, folder: Vec<String>) {
+        self.folder = folder;
+    }
+
+    /// Return whether the feed has been removed for good, e.g. because it responded with 410 Gone.
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Set whether the feed has been removed for good.
+    pub fn set_dead(&mut self, dead: bool) {
+        self.dead = dead;
+    }
+
+    /// Return the tags of the subscription.
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// Tag the subscription. Tags are compared ignoring case, so adding an existing tag does nothing.
+    pub fn add_tag(&mut self, tag: &str) {
+        let tag = tag.trim();
+        if !tag.is_empty() && !self.has_tag(tag) {
+            self.tags.push(tag.to_string());
+        }
+    }
+
+    /// Remove the given tag from the subscription, ignoring case.
+    pub fn remove_tag(&mut self, tag: &str) {
+        self.tags
+            .retain(|existing| !existing.eq_ignore_ascii_case(tag.trim()));
+    }
+
+    /// Return whether the subscription is tagged with the given tag, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|existing| existing.eq_ignore_ascii_case(tag.trim()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_tags() {
+        let mut subscription = Subscription::new("https://example.com/feed");
+        subscription.add_tag("Rust");
+        subscription.add_tag("rust");
+        subscription.add_tag(" ");
+        assert_eq!(subscription.tags(), ["Rust"]);
+        assert!(subscription.has_tag("RUST"));
+
+        subscription.remove_tag("rust");
+        assert!(subscription.tags().is_empty());
+    }
+}